@@ -0,0 +1,88 @@
+// EXT
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaChaRng;
+
+// UU
+use crate::moves::Move;
+use crate::state::GameState;
+
+/// Something that decides moves on behalf of a seat. Agents may play at tables on other threads.
+pub trait Agent: Send {
+    fn name(&self) -> String;
+
+    /// Picks the index of one of `moves`, which are always the legal moves of `player` in `state`.
+    fn choose(&mut self, state: &GameState, player: usize, moves: &[Move]) -> usize;
+
+    /// Whether the agent gave up its seat, e.g. because its process died.
+    fn forfeited(&self) -> bool {
+        return false;
+    }
+}
+
+/// Uniformly random legal moves, reproducible from its seed.
+pub struct RandomAgent {
+    rng: ChaChaRng
+}
+
+impl RandomAgent {
+    pub fn new(seed: u64) -> Self {
+        return RandomAgent { rng: ChaChaRng::seed_from_u64(seed) };
+    }
+}
+
+impl Agent for RandomAgent {
+    fn name(&self) -> String {
+        return "random".to_string();
+    }
+
+    fn choose(&mut self, _state: &GameState, _player: usize, moves: &[Move]) -> usize {
+        return self.rng.gen_range(0..moves.len());
+    }
+}
+
+/// Always takes the first legal move, handy for predictable games.
+pub struct FirstAgent {}
+
+impl Agent for FirstAgent {
+    fn name(&self) -> String {
+        return "first".to_string();
+    }
+
+    fn choose(&mut self, _state: &GameState, _player: usize, _moves: &[Move]) -> usize {
+        return 0;
+    }
+}
+
+/// Built-in agent called `name`, or None when there is no such agent.
+pub fn agent_by_name(name: &str, seed: u64) -> Option<Box<dyn Agent>> {
+    return match name {
+        "random" => Some(Box::new(RandomAgent::new(seed))),
+        "first" => Some(Box::new(FirstAgent {})),
+        _ => None
+    };
+}
+
+#[cfg(test)]
+mod AgentTest {
+    use super::*;
+    use crate::game::Game;
+    use crate::moves::next_to_act;
+    use crate::state::Board;
+
+    fn play_out(agent: &mut dyn Agent, seed: [u8; 32]) -> Vec<Move> {
+        let mut game = Game::new(&Board::new_base_game(3), true, Some(seed)).unwrap();
+        while let Some((player, moves)) = next_to_act(game.current_state()) {
+            let choice = agent.choose(game.current_state(), player, &moves);
+            game.apply(&moves[choice]).unwrap();
+        }
+        return game.moves().clone();
+    }
+
+    #[test]
+    fn test_random_agent_is_reproducible() {
+        let first = play_out(&mut RandomAgent::new(11), [1; 32]);
+        let second = play_out(&mut RandomAgent::new(11), [1; 32]);
+        assert!(!first.is_empty());
+        assert!(first == second);
+    }
+}
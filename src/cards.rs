@@ -1,17 +1,18 @@
 // STD
-use std::any::{Any, TypeId};
+use std::any::Any;
 use std::fmt;
 use std::fmt::Debug;
-use std::rc::Rc;
 
 // EXT
 use dyn_clone::DynClone;
+use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
 
 // UU
-use crate::state::{Action, ActionType, Board, GameState, History, PhaseType, ReactResult, ReactAction, ResponseOp};
+use crate::definitions::{definitions, find, CardDef};
+use crate::state::PhaseType;
 
-#[derive(Debug, Clone)]
-enum CardType {
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum CardType {
     Null,
     Instant,
     Magic,
@@ -19,31 +20,32 @@ enum CardType {
     Upgrade,
     BasicUnicorn,
     MagicUnicorn,
-    BabyUnicorn
+    BabyUnicorn,
+    // Sits in a stable like a unicorn but is not one.
+    Dragon
 }
 
 impl CardType {
-    fn is_unicorn(&self) -> bool {
-        match self {
-            CardType::BasicUnicorn => { true },
-            CardType::MagicUnicorn => { true },
-            CardType::BabyUnicorn => { true },
-            _ => { false }
-        }
+    pub fn is_unicorn(&self) -> bool {
+        matches!(self, CardType::BasicUnicorn | CardType::MagicUnicorn | CardType::BabyUnicorn)
     }
 }
 
-pub trait Card: Debug + DynClone {
-    fn ctype(&self) -> CardType;
-    fn name(&self) -> &'static str;
+pub trait Card: Debug + DynClone + Send + Sync {
+    /// Data this card is built from.
+    fn definition(&self) -> &'static CardDef;
 
-    // Always assumes card has already been taken from the hand.
-    fn play(self: Box<Self>, player: usize, cur_state: &GameState, history: &History) -> ReactResult { Ok(vec![]) }
-    fn react(self: Box<Self>, player: usize, cur_state: &GameState, history: &History) -> ReactResult { Ok(vec![]) }
+    fn ctype(&self) -> CardType { self.definition().ctype.clone() }
+    fn name(&self) -> &'static str { self.definition().name.as_str() }
 
-    fn effect(&self, player: usize, cur_state: &GameState, history: &History) -> ReactResult { Ok(vec![]) }
-    fn destroy(&self, player: usize, cur_state: &GameState, history: &History) -> ReactResult { Ok(vec![]) }
-    fn steal(&self, player: usize, cur_state: &GameState, history: &History) -> ReactResult { Ok(vec![]) }
+    fn is_rainbow(&self) -> bool { self.definition().rainbow }
+    fn is_legendary(&self) -> bool { self.definition().legendary }
+
+    /// Whether the card goes into a stable when played, rather than the discard pile.
+    fn is_permanent(&self) -> bool {
+        let ctype = self.ctype();
+        return ctype.is_unicorn() || ctype == CardType::Upgrade || ctype == CardType::Downgrade || ctype == CardType::Dragon;
+    }
 
     /// Determines if the current card can play in a start phase.
     fn phase_playable(&self) -> &'static [PhaseType] {
@@ -65,238 +67,87 @@ dyn_clone::clone_trait_object!(Card);
 
 pub type Cards = Vec<Box<dyn Card>>;
 type CardItem = Box<dyn Card>;
-pub trait QueryCards {
-
-    /// Remove Card from cards.
-    fn remove_one_card_with_type<T: 'static + Card>(&self) -> Option<(CardItem, Cards)>;
 
-    /// Check if card type exists
-    fn has_card<T: 'static + Card>(&self) -> bool;
-
-    /// Counts cards of specific type.
-    fn count_card<T: 'static + Card>(&self) -> usize;
+/// One copy of every known card.
+#[allow(dead_code)]
+pub fn all_cards() -> Cards {
+    return definitions().into_iter().map(|d| Box::new(DefinedCard::new(d)) as CardItem).collect();
 }
 
-impl QueryCards for Cards {
-    fn remove_one_card_with_type<T: 'static + Card>(&self) -> Option<(Box<dyn Card>, Cards)> {
-        for (idx, c) in self.iter().enumerate() {
-           if c.as_any().is::<T>() {
-                let mut new_qc = self.clone();
-                new_qc.remove(idx);
-                return Some((c.clone(), new_qc));
-            }
-        }
-        return None;
-    }
-
-    fn has_card<T: 'static + Card>(&self) -> bool {
-        return self.iter().any(|x| x.as_any().is::<T>());
-    }
+/// Builds a fresh copy of the known card called `name`.
+pub fn card_from_name(name: &str) -> Option<CardItem> {
+    return find(name).map(|d| Box::new(DefinedCard::new(d)) as CardItem);
+}
 
-    fn count_card<T: 'static + Card>(&self) -> usize {
-        return self.iter().filter(|x| x.as_any().is::<T>()).count();
-    }
+/// Builds the card called `name` out of the definitions in `text` without registering any of them,
+/// so cards made up for one test stay out of every other test's way.
+#[cfg(test)]
+pub fn unregistered_card(text: &str, name: &str) -> CardItem {
+    let defs = crate::definitions::parse(text).expect("test card definitions should be valid");
+    let def = defs.into_iter().find(|d| d.name == name).expect("test card should be defined");
+    return Box::new(DefinedCard::new(Box::leak(Box::new(def))));
 }
 
-#[derive(Debug, Clone)]
-pub struct BasicUnicorn {}
-impl Card for BasicUnicorn {
-    fn ctype(&self) -> CardType { CardType::BasicUnicorn }
-    fn name(&self) -> &'static str { "Basic Unicorn" }
-    fn play(self: Box<Self>, player: usize, cur_state: &GameState, history: &History) -> ReactResult {
-        let mut latest_board = cur_state.board.clone();
-        latest_board.players[player].stable.push(self.clone());
-
-        return Ok(vec![
-            ReactAction::from(
-                &Action {
-                    card: self,
-                    atype: ActionType::Place,
-                    board: latest_board,
-                }
-            )
-        ]);
+// Cards carry no state of their own, so their name is enough to serialize them.
+impl Serialize for Box<dyn Card> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        return serializer.serialize_str(self.name());
     }
-
-    fn as_any(&self) -> &dyn Any { self }
 }
 
-#[derive(Debug, Clone)]
-pub struct UnicornPhoenix {}
-impl Card for UnicornPhoenix {
-    fn ctype(&self) -> CardType { CardType::MagicUnicorn }
-    fn name(&self) -> &'static str { "Unicorn Phoenix" }
-    fn play(self: Box<Self>, player: usize, cur_state: &GameState, history: &History) -> ReactResult {
-        let mut latest_board = cur_state.board.clone();
-        latest_board.players[player].stable.push(self.clone());
-
-        return Ok(vec![
-            ReactAction::from(
-                &Action {
-                    card: self,
-                    atype: ActionType::Place,
-                    board: latest_board,
-                }
-            )
-        ]);
+impl<'de> Deserialize<'de> for Box<dyn Card> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let name = String::deserialize(deserializer)?;
+        return card_from_name(&name).ok_or_else(|| de::Error::custom(format!("unknown card {:?}", name)));
     }
+}
+pub trait QueryCards {
 
-    fn effect(&self, player: usize, cur_state: &GameState, history: &History) -> ReactResult {
-        let last_action = history.last().unwrap();
-        if !last_action.card.as_any().is::<Self>() {
-            return Ok(vec![]);
-        }
-        // Check the type of effect.
-        let mut final_result = vec![];
-        if last_action.atype == ActionType::Destroy || last_action.atype == ActionType::Sacrifice {
-            let mut new_board = cur_state.board.clone();
-            let own_card = new_board.discard.pop().unwrap();
-            assert!(own_card.as_any().is::<Self>(), "Invalid discard stack detected.");
-            new_board.players[player].stable.push(own_card);
-            let effect_action = Action {
-                card: last_action.card.clone(),
-                atype: ActionType::Revive,
-                board: new_board
-            };
-
-            let hand = &cur_state.board.players[player].hand;
-            hand.iter().enumerate().map(|(idx, h)| {
-                let mut new_board = cur_state.board.clone();
-                new_board.players[player].hand.remove(idx);
-                new_board.discard.push(h.clone());
-
-
-                final_result.push(ReactAction {
-                    effect_action: effect_action.clone(),
-                    follow_up: Some(ResponseOp::Discard),
-                    response_user: vec![]
-                });
-            }).count();
-        }
+    /// Remove Card from cards.
+    fn remove_one_card_named(&self, name: &str) -> Option<(CardItem, Cards)>;
 
-        return Ok(final_result);
-    }
+    /// Check if card exists
+    // Only the tests look cards up by name so far.
+    #[allow(dead_code)]
+    fn has_card(&self, name: &str) -> bool;
 
-    fn as_any(&self) -> &dyn Any { self }
+    /// Counts cards with the given name.
+    #[allow(dead_code)]
+    fn count_card(&self, name: &str) -> usize;
 }
 
-#[derive(Debug, Clone)]
-pub struct BabyUnicorn {}
-impl Card for BabyUnicorn {
-    fn ctype(&self) -> CardType { CardType::BabyUnicorn }
-    fn name(&self) -> &'static str { "Baby Unicorn" }
-    fn play(self: Box<Self>, player: usize, cur_state: &GameState, history: &History) -> ReactResult {
-        let mut latest_board = cur_state.board.clone();
-        latest_board.players[player].stable.push(self.clone());
-
-        return Ok(vec![
-            ReactAction::from(
-                &Action {
-                    card: self,
-                    atype: ActionType::Place,
-                    board: latest_board,
-                }
-            )
-        ]);
+impl QueryCards for Cards {
+    fn remove_one_card_named(&self, name: &str) -> Option<(Box<dyn Card>, Cards)> {
+        let idx = self.iter().position(|c| c.name() == name)?;
+        let mut new_qc = self.clone();
+        let card = new_qc.remove(idx);
+        return Some((card, new_qc));
     }
 
-    fn as_any(&self) -> &dyn Any { self }
-}
-
-
-#[derive(Debug, Clone)]
-pub struct SuperNeigh {}
-impl Card for SuperNeigh {
-    fn ctype(&self) -> CardType { CardType::Instant }
-    fn name(&self) -> &'static str { "Super Neigh" }
-    fn play(self: Box<Self>, player: usize, cur_state: &GameState, history: &History) -> ReactResult {
-        let mut latest_board = cur_state.board.clone();
-        latest_board.discard.push(self.clone());
-
-        return Ok(vec![
-            ReactAction::from(
-                &Action {
-                    card: self,
-                    atype: ActionType::Instant,
-                    board: latest_board
-                }
-            )
-        ]);
+    fn has_card(&self, name: &str) -> bool {
+        return self.iter().any(|x| x.name() == name);
     }
 
-    fn as_any(&self) -> &dyn Any { self }
+    fn count_card(&self, name: &str) -> usize {
+        return self.iter().filter(|x| x.name() == name).count();
+    }
 }
 
+/// A card that does what its definition says, see `definitions` and `effects`.
 #[derive(Debug, Clone)]
-pub struct Neigh {}
-impl Card for Neigh {
-    fn ctype(&self) -> CardType { CardType::Instant }
-    fn name(&self) -> &'static str { "Neigh" }
-    fn react(self: Box<Self>, player: usize, cur_state: &GameState, history: &History) -> ReactResult {
-        if history.len() < 1 {
-            // Cannot play instant without a reaction.
-            return Ok(vec![]);
-        }
-
-        let latest_action = &history.last().unwrap();
-        if latest_action.card.as_any().is::<SuperNeigh>() {
-            return Ok(vec![]);
-        }
+pub struct DefinedCard {
+    def: &'static CardDef
+}
 
-        let mut latest_board = latest_action.board.clone();
-        latest_board.discard.push(self.clone());
-
-        return Ok(vec![
-            ReactAction::from(
-                &Action {
-                    card: self,
-                    atype: ActionType::Instant,
-                    board: latest_board
-                }
-            )
-        ]);
+impl DefinedCard {
+    pub fn new(def: &'static CardDef) -> Self {
+        return DefinedCard { def };
     }
-
-    fn as_any(&self) -> &dyn Any { self }
 }
 
-#[derive(Debug, Clone)]
-pub struct UnicornPoison {}
-impl Card for UnicornPoison {
-    fn ctype(&self) -> CardType { CardType::Magic }
-    fn name(&self) -> &'static str { "Unicorn Poison" }
-    fn play(self: Box<Self>, player: usize, cur_state: &GameState, history: &History) -> ReactResult {
-        let mut latest_board = cur_state.board.clone();
-        latest_board.discard.push(self.clone());
-
-        let mut result = vec![];
-
-        let effect_action = Action {
-            card: self,
-            atype: ActionType::Discard,
-            board: latest_board.clone(),
-        };
-
-        for (p_idx, p) in latest_board.players.iter().enumerate() {
-            for (idx, k) in p.stable.iter().enumerate() {
-                if !k.ctype().is_unicorn() {
-                    continue
-                }
-
-                let mut follow_board = latest_board.clone();
-                follow_board.players[p_idx].stable.remove(idx);
-                follow_board.discard.push(k.clone());
-
-                result.push(ReactAction {
-                    follow_up: Some(ResponseOp::Destroy),
-                    effect_action: effect_action.clone(),
-                    response_user: vec![]
-                })
-            }
-        }
+impl Card for DefinedCard {
+    fn definition(&self) -> &'static CardDef { self.def }
 
-        return Ok(result);
-    }
     fn as_any(&self) -> &dyn Any { self }
 }
 
@@ -304,6 +155,7 @@ impl Card for UnicornPoison {
 #[cfg(test)]
 mod CardTest {
     use super::*;
+    use crate::state::{Action, ActionType, Board};
 
     fn default_board() -> Board {
         return Board::new_base_game(2);
@@ -312,65 +164,49 @@ mod CardTest {
     #[test]
     fn test_has_card() {
         let board = default_board();
-        assert!(board.deck.has_card::<Neigh>(), "Should contain Neigh");
-        assert!(board.deck.has_card::<SuperNeigh>(), "Should contain SuperNeigh");
+        assert!(board.deck.has_card("Neigh"), "Should contain Neigh");
+        assert!(board.deck.has_card("Super Neigh"), "Should contain SuperNeigh");
     }
 
+    #[test]
     fn test_count_card() {
         let board = default_board();
-        assert!(board.deck.count_card::<Neigh>() == 3, "Should contain Neigh");
+        assert!(board.deck.count_card("Neigh") == 3, "Should contain Neigh");
     }
 
     #[test]
     fn test_neigh_neigh() {
         let board = default_board();
         let neigh_action = Action {
-            card: board.draw_specific_card::<Neigh>().unwrap().unwrap().card,
+            card: board.draw_specific_card("Neigh").unwrap().unwrap().card,
             atype: ActionType::Instant,
             board: board.clone()
         };
 
-        let game_state = GameState {
-            board,
-            phase: PhaseType::Play,
-            react_metadata: None
-        };
-
         // Force a neigh on the neigh
-        let forced_neigh = Box::new(Neigh {});
-        let option = forced_neigh.react(0,
-                                        &game_state,
-                                        &vec![neigh_action]).unwrap();
-        assert!(option.len() == 1);
-        let option = &option[0];
-
-        assert!(option.effect_action.card.as_any().is::<Neigh>());
-        assert!(option.effect_action.board.discard.len() == 1);
-        assert!(option.effect_action.board.discard.has_card::<Neigh>());
+        let forced_neigh = card_from_name("Neigh").unwrap();
+        let counter = forced_neigh.definition().counter.clone().unwrap();
+        assert!(counter.answers(&vec![neigh_action]));
+        assert!(!counter.answers(&vec![]), "Cannot play instant without a reaction.");
     }
 
     #[test]
     fn test_neigh_super_neigh() {
         let board = default_board();
         let neigh_action = Action {
-            card: board.draw_specific_card::<SuperNeigh>().unwrap().unwrap().card,
+            card: board.draw_specific_card("Super Neigh").unwrap().unwrap().card,
             atype: ActionType::Instant,
             board: board.clone()
         };
 
-        let game_state = GameState {
-            board,
-            phase: PhaseType::Play,
-            react_metadata: None
-        };
-
         // Force a neigh on the neigh
-        let forced_neigh = Box::new(Neigh {});
-        let option = forced_neigh.react(0, &game_state, &vec![neigh_action]);
-        assert!(option.unwrap().len() == 0, "Cannot neigh a super neigh.");
+        let forced_neigh = card_from_name("Neigh").unwrap();
+        let counter = forced_neigh.definition().counter.clone().unwrap();
+        assert!(!counter.answers(&vec![neigh_action]), "Cannot neigh a super neigh.");
     }
 
     #[test]
+    #[allow(clippy::bool_comparison)]
     fn test_is_unicorn() {
         assert!(CardType::BasicUnicorn.is_unicorn() == true);
         assert!(CardType::MagicUnicorn.is_unicorn() == true);
@@ -380,4 +216,10 @@ mod CardTest {
         assert!(CardType::Downgrade.is_unicorn() == false);
         assert!(CardType::Upgrade.is_unicorn() == false);
     }
+
+    #[test]
+    fn test_dragon_type() {
+        assert!(!CardType::Dragon.is_unicorn());
+        assert!(card_from_name("Red Dragon").unwrap().is_permanent());
+    }
 }
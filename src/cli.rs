@@ -0,0 +1,593 @@
+// STD
+use std::fmt;
+use std::io::{self, BufRead, Write};
+use std::net::TcpListener;
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::time::Duration;
+
+// EXT
+use clap::{Parser, Subcommand};
+
+// UU
+use crate::agent::{agent_by_name, Agent};
+use crate::definitions::{load_file, DefinitionError, Set};
+use crate::external::ExternalAgent;
+use crate::game::{seed_bytes, Game};
+use crate::moves::next_to_act;
+use crate::lobby::{host, Lobby};
+use crate::notation::format_move;
+use crate::replay::{Replay, ReplayError};
+use crate::save::{load_game, CardSources, SaveError, DEFAULT_SAVE};
+use crate::server::{serve, ServerConfig};
+use crate::state::{Board, LogicError, Rules};
+use crate::terminal::{describe_result, play_hot_seat, render_all, render_view};
+use crate::tui::{self, Seat};
+
+#[derive(Parser, Debug)]
+#[command(name = "unstable_unicorns", about = "Unstable Unicorns, played, simulated and replayed from the terminal.")]
+pub struct Cli {
+    /// Card definition files to load on top of the built-in cards.
+    #[arg(long, global = true)]
+    pub cards: Vec<PathBuf>,
+    /// Card sets new games are dealt from, e.g. `base,dragons`.
+    #[arg(long, global = true, value_delimiter = ',', default_value = "base")]
+    pub sets: Vec<Set>,
+    // Defaults to `play` when left out.
+    #[command(subcommand)]
+    pub command: Option<Command>
+}
+
+#[derive(Subcommand, Debug, PartialEq)]
+pub enum Command {
+    /// Play at this keyboard, hot-seat style.
+    Play {
+        /// Number of players, asked for when left out.
+        #[arg(short, long, value_parser = clap::value_parser!(u8).range(2..=8))]
+        players: Option<u8>,
+        /// Seed for shuffling, random when left out.
+        #[arg(long)]
+        seed: Option<u64>,
+        /// Use the full-screen interface.
+        #[arg(long)]
+        tui: bool,
+        /// Who sits at each seat, e.g. `human,random`. Agents need --tui.
+        #[arg(long, value_delimiter = ',')]
+        seats: Vec<SeatKind>,
+        /// Continue a saved game, dealt from the cards it was saved with.
+        #[arg(long, conflicts_with_all = ["players", "seed"])]
+        load: Option<PathBuf>
+    },
+    /// Play many games between agents and report how each seat did.
+    Simulate {
+        /// Number of games to play.
+        #[arg(short = 'n', long, default_value_t = 100)]
+        games: u64,
+        #[arg(short, long, default_value_t = 2, value_parser = clap::value_parser!(u8).range(2..=8))]
+        players: u8,
+        /// Agents for each seat, repeated when there are more seats than agents.
+        /// Built-in agents are `random` and `first`, `bot:<program> [args]` runs an external bot.
+        #[arg(long, value_delimiter = ',', default_value = "random")]
+        agents: Vec<SeatKind>,
+        /// Seed of the first game, each further game uses the next one.
+        #[arg(long, default_value_t = 0)]
+        seed: u64,
+        /// Games still running after this many moves are given up on.
+        #[arg(long, default_value_t = 2000)]
+        max_moves: usize,
+        /// Milliseconds an external bot gets per move.
+        #[arg(long, default_value_t = 1000)]
+        move_time_ms: u64,
+        /// Directory to write a replay of every game to.
+        #[arg(long)]
+        record: Option<PathBuf>
+    },
+    /// Check a recorded game and print its moves.
+    Replay {
+        file: PathBuf,
+        /// Show the board after every move and wait for enter.
+        #[arg(long)]
+        step: bool
+    },
+    /// Print everything in a saved game.
+    Inspect {
+        save: PathBuf
+    },
+    /// Host a game over TCP, one connection per seat in the order they connect.
+    Serve {
+        #[arg(long, default_value = "127.0.0.1:7777")]
+        addr: String,
+        #[arg(short, long, default_value_t = 2, value_parser = clap::value_parser!(u8).range(2..=8))]
+        players: u8,
+        #[arg(long)]
+        seed: Option<u64>,
+        /// Milliseconds a seat gets to decide whether to neigh.
+        #[arg(long, default_value_t = 10000)]
+        react_timeout_ms: u64,
+        /// Milliseconds a seat gets for any other move, unlimited when left out.
+        #[arg(long)]
+        move_timeout_ms: Option<u64>
+    },
+    /// Host a lobby where clients create and join tables, many games at once.
+    Lobby {
+        #[arg(long, default_value = "127.0.0.1:7777")]
+        addr: String,
+        /// Seed picking the seeds of tables created without one.
+        #[arg(long)]
+        seed: Option<u64>,
+        #[arg(long, default_value_t = 10000)]
+        react_timeout_ms: u64,
+        #[arg(long)]
+        move_timeout_ms: Option<u64>
+    }
+}
+
+/// Who plays a seat: `human`, a built-in agent such as `random`, or `bot:<program> [args]`
+/// for an external bot speaking JSON lines over stdio.
+#[derive(Clone, Debug, PartialEq)]
+pub enum SeatKind {
+    Human,
+    Builtin(String),
+    External(Vec<String>)
+}
+
+impl FromStr for SeatKind {
+    type Err = String;
+
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        if let Some(command) = text.strip_prefix("bot:") {
+            let command: Vec<_> = command.split_whitespace().map(String::from).collect();
+            if command.is_empty() {
+                return Err("bot: needs a program to run".to_string());
+            }
+            return Ok(SeatKind::External(command));
+        }
+
+        return match text {
+            "human" => Ok(SeatKind::Human),
+            name if agent_by_name(name, 0).is_some() => Ok(SeatKind::Builtin(name.to_string())),
+            _ => Err(format!("{:?} is not human, random, first or bot:<program>", text))
+        };
+    }
+}
+
+impl SeatKind {
+    fn label(&self) -> String {
+        return match self {
+            SeatKind::Human => "human".to_string(),
+            SeatKind::Builtin(name) => name.clone(),
+            SeatKind::External(command) => {
+                Path::new(&command[0]).file_name().map_or(command[0].clone(), |n| n.to_string_lossy().to_string())
+            }
+        };
+    }
+}
+
+#[derive(Debug)]
+pub enum CliError {
+    // Arguments clap accepts but that do not make sense together.
+    Usage(String),
+    Io(io::Error),
+    Logic(LogicError),
+    Save(SaveError),
+    Replay(ReplayError),
+    Cards(DefinitionError)
+}
+
+impl fmt::Display for CliError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CliError::Usage(message) => write!(f, "{}", message),
+            CliError::Io(e) => write!(f, "{}", e),
+            CliError::Logic(e) => write!(f, "{}", e),
+            CliError::Save(e) => write!(f, "{}", e),
+            CliError::Replay(e) => write!(f, "{}", e),
+            CliError::Cards(e) => write!(f, "{}", e)
+        }
+    }
+}
+
+impl std::error::Error for CliError {}
+
+impl From<io::Error> for CliError {
+    fn from(e: io::Error) -> Self {
+        return CliError::Io(e);
+    }
+}
+
+fn new_agent(kind: &SeatKind, seed: u64, time_limit: Duration) -> Result<Option<Box<dyn Agent>>, CliError> {
+    return match kind {
+        SeatKind::Human => Ok(None),
+        SeatKind::Builtin(name) => Ok(agent_by_name(name, seed)),
+        SeatKind::External(command) => {
+            let bot = ExternalAgent::spawn(&command[0], &command[1..], time_limit)?;
+            Ok(Some(Box::new(bot)))
+        }
+    };
+}
+
+/// How one simulated game went.
+#[derive(Clone, Debug, PartialEq)]
+pub struct GameOutcome {
+    pub seed: u64,
+    pub winner: Option<usize>,
+    pub moves: usize,
+    // Stopped at the move limit rather than running out of moves.
+    pub unfinished: bool,
+    // Seats whose agent gave up, they cannot win.
+    pub forfeits: Vec<usize>
+}
+
+/// Totals over a batch of simulated games.
+#[derive(Clone, Debug)]
+pub struct SimulationStats {
+    pub agents: Vec<String>,
+    pub outcomes: Vec<GameOutcome>
+}
+
+impl fmt::Display for SimulationStats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let games = self.outcomes.len();
+        let (first, last) = (self.outcomes.first().map_or(0, |o| o.seed), self.outcomes.last().map_or(0, |o| o.seed));
+        writeln!(f, "Simulated {} games of {} players, seeds {} to {}", games, self.agents.len(), first, last)?;
+        writeln!(f, "{:<6}{:<10}{:>6}{:>8}{:>10}", "Seat", "Agent", "Wins", "Win%", "Forfeits")?;
+        for (seat, agent) in self.agents.iter().enumerate() {
+            let wins = self.outcomes.iter().filter(|o| o.winner == Some(seat)).count();
+            let forfeits = self.outcomes.iter().filter(|o| o.forfeits.contains(&seat)).count();
+            let share = 100.0 * wins as f64 / games.max(1) as f64;
+            writeln!(f, "{:<6}{:<10}{:>6}{:>7.1}%{:>10}", format!("P{}", seat + 1), agent, wins, share, forfeits)?;
+        }
+
+        let unfinished = self.outcomes.iter().filter(|o| o.unfinished).count();
+        let no_winner = self.outcomes.iter().filter(|o| o.winner.is_none() && !o.unfinished).count();
+        writeln!(f, "No winner: {}, unfinished: {}", no_winner, unfinished)?;
+
+        let moves: Vec<_> = self.outcomes.iter().map(|o| o.moves).collect();
+        let average = moves.iter().sum::<usize>() as f64 / games.max(1) as f64;
+        let (min, max) = (moves.iter().min().unwrap_or(&0), moves.iter().max().unwrap_or(&0));
+        return writeln!(f, "Moves per game: average {:.1}, min {}, max {}", average, min, max);
+    }
+}
+
+/// Plays one game between `agents` for every seed in `seeds`, dealt from `sets`.
+/// Agents fill the seats in order, starting over when there are more seats than agents.
+/// External bots are started afresh for every game and get `time_limit` per move.
+pub fn simulate(seeds: Range<u64>, player_count: u8, sets: &[Set], agents: &[SeatKind], max_moves: usize, time_limit: Duration,
+                record: Option<&Path>) -> Result<SimulationStats, CliError> {
+    if agents.is_empty() || agents.contains(&SeatKind::Human) {
+        return Err(CliError::Usage("simulations need at least one agent and no humans".to_string()));
+    }
+    check_sets(sets)?;
+    let kinds: Vec<_> = (0..player_count as usize).map(|p| agents[p % agents.len()].clone()).collect();
+
+    let mut outcomes = vec![];
+    for game_seed in seeds {
+        let mut seats = vec![];
+        for (p, kind) in kinds.iter().enumerate() {
+            seats.push(new_agent(kind, game_seed.wrapping_add(p as u64), time_limit)?.unwrap());
+        }
+        let mut replay = Replay::new(seed_bytes(game_seed), sets, player_count, &Rules::for_players(player_count as usize));
+        let mut game = replay.start().map_err(CliError::Replay)?;
+
+        let mut unfinished = false;
+        let mut forfeits = vec![];
+        while let Some((player, moves)) = next_to_act(game.current_state()) {
+            if replay.moves.len() >= max_moves {
+                unfinished = true;
+                break;
+            }
+            let choice = seats[player].choose(game.current_state(), player, &moves).min(moves.len() - 1);
+            if seats[player].forfeited() && !forfeits.contains(&player) {
+                forfeits.push(player);
+            }
+            replay.record(&mut game, &moves[choice]).map_err(CliError::Logic)?;
+        }
+
+        if let Some(dir) = record {
+            replay.save(&dir.join(format!("game_{}.json", game_seed))).map_err(CliError::Replay)?;
+        }
+        let winner = game.current_state().winner().filter(|w| !forfeits.contains(w));
+        outcomes.push(GameOutcome { seed: game_seed, winner, moves: replay.moves.len(), unfinished, forfeits });
+    }
+
+    let agents = kinds.iter().map(SeatKind::label).collect();
+    return Ok(SimulationStats { agents, outcomes });
+}
+
+/// Refuses sets nothing is known about, their games would be dealt without cards.
+fn check_sets(sets: &[Set]) -> Result<(), CliError> {
+    if sets.is_empty() {
+        return Err(CliError::Usage("at least one card set is needed".to_string()));
+    }
+    if let Some(set) = sets.iter().find(|s| s.cards().is_empty()) {
+        return Err(CliError::Usage(format!("no cards are known for the {} set, load them with --cards", set)));
+    }
+    return Ok(());
+}
+
+// Who sits at each of `player_count` seats, humans wherever no agent is given.
+fn seat_table(seats: &[SeatKind], player_count: u8, seed: u64) -> Result<Vec<Seat>, CliError> {
+    if seats.len() > player_count as usize {
+        return Err(CliError::Usage(format!("{} seats given for {} players", seats.len(), player_count)));
+    }
+    let mut table = vec![];
+    for p in 0..player_count as usize {
+        let kind = seats.get(p).cloned().unwrap_or(SeatKind::Human);
+        // People watching can wait for external bots longer than simulations do.
+        let agent = new_agent(&kind, seed.wrapping_add(p as u64), Duration::from_secs(30))?;
+        table.push(agent.map_or(Seat::Human, Seat::Bot));
+    }
+    return Ok(table);
+}
+
+fn check_agents(use_tui: bool, seats: &[SeatKind]) -> Result<(), CliError> {
+    if seats.iter().any(|s| *s != SeatKind::Human) && !use_tui {
+        return Err(CliError::Usage("agents can only take seats in the --tui interface".to_string()));
+    }
+    return Ok(());
+}
+
+fn play<R: BufRead, W: Write>(players: Option<u8>, seed: Option<u64>, use_tui: bool, seats: &[SeatKind], sources: &CardSources,
+                              input: &mut R, output: &mut W) -> Result<(), CliError> {
+    let seed = seed.unwrap_or_else(rand::random);
+    check_agents(use_tui, seats)?;
+    let save_to = Path::new(DEFAULT_SAVE);
+
+    if !use_tui {
+        let Some(player_count) = players else {
+            return Ok(crate::terminal::run(input, output, seed_bytes(seed), sources, save_to)?);
+        };
+        let board = Board::new_with_sets(&sources.sets, player_count);
+        let mut game = Game::new(&board, true, Some(seed_bytes(seed))).map_err(CliError::Logic)?;
+        return Ok(play_hot_seat(&mut game, sources, save_to, input, output)?);
+    }
+
+    // Seats decide the table size unless it is given.
+    let player_count = players.unwrap_or((seats.len() as u8).max(2));
+    let table = seat_table(seats, player_count, seed)?;
+    let game = Game::new(&Board::new_with_sets(&sources.sets, player_count), true, Some(seed_bytes(seed))).map_err(CliError::Logic)?;
+    return Ok(tui::run(game, table, sources.clone(), save_to.to_path_buf())?);
+}
+
+// Continues the game saved at `path` with the cards it was saved with, saving back to the same file.
+fn resume<R: BufRead, W: Write>(path: &Path, use_tui: bool, seats: &[SeatKind], input: &mut R, output: &mut W) -> Result<(), CliError> {
+    check_agents(use_tui, seats)?;
+    let save = load_game(path).map_err(CliError::Save)?;
+    let sources = save.sources.clone();
+    let player_count = save.state.board.players.len() as u8;
+    let mut game = save.into_game();
+
+    if !use_tui {
+        return Ok(play_hot_seat(&mut game, &sources, path, input, output)?);
+    }
+    let table = seat_table(seats, player_count, rand::random())?;
+    return Ok(tui::run(game, table, sources, path.to_path_buf())?);
+}
+
+fn replay<R: BufRead, W: Write>(file: &Path, step: bool, input: &mut R, output: &mut W) -> Result<(), CliError> {
+    let replay = Replay::load(file).map_err(CliError::Replay)?;
+    let states = replay.replay().map_err(CliError::Replay)?;
+    writeln!(output, "Replay of {} players, {} moves, every checksum matches.", replay.player_count, replay.moves.len())?;
+
+    for (index, mv) in replay.moves.iter().enumerate() {
+        writeln!(output, "{:>4}. {}", index + 1, format_move(&states[index], mv))?;
+        if step {
+            write!(output, "{}Enter for the next move, q to stop. ", render_view(&states[index + 1], mv.player))?;
+            output.flush()?;
+            let mut line = String::new();
+            if input.read_line(&mut line)? == 0 || line.trim() == "q" {
+                return Ok(());
+            }
+        }
+    }
+
+    writeln!(output, "{}", describe_result(states.last().unwrap()))?;
+    return Ok(());
+}
+
+fn inspect<W: Write>(path: &Path, output: &mut W) -> Result<(), CliError> {
+    let save = load_game(path).map_err(CliError::Save)?;
+    let state = &save.state;
+
+    writeln!(output, "Save version {}, {} moves played", save.version, save.moves.len())?;
+    let sets: Vec<_> = save.sources.sets.iter().map(Set::to_string).collect();
+    writeln!(output, "Sets: {}", sets.join(", "))?;
+    for file in &save.sources.files {
+        writeln!(output, "Cards from {}", file.display())?;
+    }
+    writeln!(output, "Rules: {} unicorns to win, hand limit {}", state.rules.unicorns_to_win, state.rules.hand_limit)?;
+    write!(output, "{}", render_all(state))?;
+    match next_to_act(state) {
+        Some((player, moves)) => {
+            let moves: Vec<_> = moves.iter().map(|m| format_move(state, m)).collect();
+            writeln!(output, "Waiting on P{}: {}", player + 1, moves.join(", "))?;
+        },
+        None => writeln!(output, "{}", describe_result(state))?
+    }
+    return Ok(());
+}
+
+/// Registers the cards in every file of `paths`, in order.
+pub fn load_cards(paths: &[PathBuf]) -> Result<(), CliError> {
+    for path in paths {
+        load_file(path).map_err(CliError::Cards)?;
+    }
+    return Ok(());
+}
+
+fn server_config(react_timeout_ms: u64, move_timeout_ms: Option<u64>) -> ServerConfig {
+    return ServerConfig {
+        react_timeout: Duration::from_millis(react_timeout_ms),
+        move_timeout: move_timeout_ms.map(Duration::from_millis)
+    };
+}
+
+/// Runs `command`, dealing new games from the cards of `sources` and reading answers from `input` for the interactive ones.
+pub fn run<R: BufRead, W: Write>(command: Command, sources: &CardSources, input: &mut R, output: &mut W) -> Result<(), CliError> {
+    let sets = &sources.sets;
+    check_sets(sets)?;
+    return match command {
+        Command::Play { tui, seats, load: Some(path), .. } => resume(&path, tui, &seats, input, output),
+        Command::Play { players, seed, tui, seats, load: None } => play(players, seed, tui, &seats, sources, input, output),
+        Command::Simulate { games, players, agents, seed, max_moves, move_time_ms, record } => {
+            let time_limit = Duration::from_millis(move_time_ms);
+            let end = seed.checked_add(games).ok_or_else(|| CliError::Usage(format!("{} games from seed {} run out of seeds", games, seed)))?;
+            let stats = simulate(seed..end, players, sets, &agents, max_moves, time_limit, record.as_deref())?;
+            Ok(write!(output, "{}", stats)?)
+        },
+        Command::Replay { file, step } => replay(&file, step, input, output),
+        Command::Inspect { save } => inspect(&save, output),
+        Command::Serve { addr, players, seed, react_timeout_ms, move_timeout_ms } => {
+            let seed = seed.unwrap_or_else(rand::random);
+            let mut game = Game::new(&Board::new_with_sets(sets, players), true, Some(seed_bytes(seed))).map_err(CliError::Logic)?;
+            let config = server_config(react_timeout_ms, move_timeout_ms);
+            let listener = TcpListener::bind(&addr)?;
+            writeln!(output, "Listening on {}, waiting for {} players (seed {}).", listener.local_addr()?, players, seed)?;
+            serve(&listener, &mut game, &config)?;
+            Ok(writeln!(output, "{}", describe_result(game.current_state()))?)
+        },
+        Command::Lobby { addr, seed, react_timeout_ms, move_timeout_ms } => {
+            let lobby = Lobby::new(seed.unwrap_or_else(rand::random), sets, server_config(react_timeout_ms, move_timeout_ms));
+            let listener = TcpListener::bind(&addr)?;
+            writeln!(output, "Lobby open on {}.", listener.local_addr()?)?;
+            Ok(host(&listener, lobby)?)
+        }
+    };
+}
+
+#[cfg(test)]
+mod CliTest {
+    use super::*;
+    use std::fs;
+    use crate::moves::{Move, MoveType};
+    use crate::save::save_game;
+
+    fn parse(args: &[&str]) -> Result<Option<Command>, clap::Error> {
+        let args = std::iter::once("unstable_unicorns").chain(args.iter().cloned());
+        return Cli::try_parse_from(args).map(|cli| cli.command);
+    }
+
+    fn card_files(args: &[&str]) -> Vec<PathBuf> {
+        let args = std::iter::once("unstable_unicorns").chain(args.iter().cloned());
+        return Cli::try_parse_from(args).unwrap().cards;
+    }
+
+    fn sets(args: &[&str]) -> Result<Vec<Set>, clap::Error> {
+        let args = std::iter::once("unstable_unicorns").chain(args.iter().cloned());
+        return Cli::try_parse_from(args).map(|cli| cli.sets);
+    }
+
+    fn temp_path(name: &str) -> PathBuf {
+        return std::env::temp_dir().join(format!("uu_cli_test_{}_{}", std::process::id(), name));
+    }
+
+    #[test]
+    fn test_parse() {
+        assert!(parse(&[]).unwrap().is_none());
+        assert!(parse(&["play", "-p", "3", "--tui", "--seats", "human,random"]).unwrap()
+                == Some(Command::Play { players: Some(3), seed: None, tui: true, seats: vec![SeatKind::Human, SeatKind::Builtin("random".to_string())], load: None }));
+        assert!(matches!(parse(&["play", "--load", "a.json"]).unwrap(), Some(Command::Play { load: Some(_), .. })));
+        assert!(parse(&["play", "--load", "a.json", "--seed", "3"]).is_err());
+        assert!(matches!(parse(&["simulate", "-n", "5", "--agents", "first"]).unwrap(),
+                         Some(Command::Simulate { games: 5, players: 2, seed: 0, .. })));
+        assert!(matches!(parse(&["inspect", "a.json"]).unwrap(), Some(Command::Inspect { .. })));
+        assert!(matches!(parse(&["serve", "--react-timeout-ms", "500"]).unwrap(),
+                         Some(Command::Serve { react_timeout_ms: 500, move_timeout_ms: None, players: 2, .. })));
+        assert!(matches!(parse(&["lobby", "--seed", "3"]).unwrap(), Some(Command::Lobby { seed: Some(3), .. })));
+        assert!(parse(&["play", "-p", "9"]).is_err());
+        assert!(parse(&["simulate", "--agents", "robot"]).is_err());
+        assert!(matches!(parse(&["simulate", "--agents", "bot:./my_bot --fast,first"]).unwrap(),
+                         Some(Command::Simulate { agents, .. }) if agents[0] == SeatKind::External(vec!["./my_bot".to_string(), "--fast".to_string()])));
+        assert!(parse(&["replay"]).is_err());
+        assert!(card_files(&["simulate", "--cards", "a.json", "--cards", "b.json"]) == vec![PathBuf::from("a.json"), PathBuf::from("b.json")]);
+        assert!(matches!(load_cards(&[temp_path("missing.json")]), Err(CliError::Cards(_))));
+        assert!(sets(&["play"]).unwrap() == vec![Set::Base]);
+        assert!(sets(&["simulate", "--sets", "base,rainbow-apocalypse"]).unwrap() == vec![Set::Base, Set::RainbowApocalypse]);
+        assert!(sets(&["--sets", "ponies", "play"]).is_err());
+    }
+
+    #[test]
+    fn test_simulate() {
+        let (random, first) = (SeatKind::Builtin("random".to_string()), SeatKind::Builtin("first".to_string()));
+        let limit = Duration::from_secs(5);
+        let stats = simulate(10..16, 3, &[Set::Base], &[random.clone(), first.clone()], 2000, limit, None).unwrap();
+        assert!(stats.agents == vec!["random", "first", "random"]);
+        assert!(stats.outcomes.len() == 6 && stats.outcomes[5].seed == 15);
+        assert!(stats.outcomes.iter().all(|o| o.moves > 0 && !o.unfinished));
+
+        let again = simulate(10..16, 3, &[Set::Base], &[random, first.clone()], 2000, limit, None).unwrap();
+        assert!(again.outcomes == stats.outcomes, "Same seeds should play the same games.");
+
+        let text = stats.to_string();
+        assert!(text.starts_with("Simulated 6 games of 3 players, seeds 10 to 15"));
+        assert!(text.contains("P2    first"));
+
+        let capped = simulate(0..1, 2, &[Set::Base], std::slice::from_ref(&first), 3, limit, None).unwrap();
+        assert!(capped.outcomes[0].unfinished && capped.outcomes[0].moves == 3);
+        assert!(matches!(simulate(0..1, 2, &[Set::Base], &[SeatKind::Human], 10, limit, None), Err(CliError::Usage(_))));
+
+        // An external bot that dies straight away forfeits every game against the built-in agent.
+        let crashing = SeatKind::from_str("bot:sh -c exit").unwrap();
+        let stats = simulate(0..3, 2, &[Set::Base], &[crashing, first], 2000, limit, None).unwrap();
+        assert!(stats.agents == vec!["sh", "first"]);
+        assert!(stats.outcomes.iter().all(|o| o.forfeits == vec![0] && o.winner != Some(0)));
+        assert!(stats.to_string().contains("P1    sh             0    0.0%         3"));
+
+        let command = parse(&["simulate", "-n", "2", "--seed", &u64::MAX.to_string()]).unwrap().unwrap();
+        let err = run(command, &CardSources::default(), &mut io::empty(), &mut vec![]);
+        assert!(matches!(err, Err(CliError::Usage(_))), "Seeds past u64::MAX do not exist.");
+    }
+
+    #[test]
+    fn test_replay_and_inspect() {
+        let dir = temp_path("games");
+        fs::create_dir_all(&dir).unwrap();
+        let command = parse(&["simulate", "-n", "1", "--seed", "4", "--record", dir.to_str().unwrap()]).unwrap().unwrap();
+        run(command, &CardSources::default(), &mut io::empty(), &mut vec![]).unwrap();
+
+        let mut output = vec![];
+        let file = dir.join("game_4.json");
+        run(Command::Replay { file: file.clone(), step: true }, &CardSources::default(), &mut "\n\nq\n".as_bytes(), &mut output).unwrap();
+        let text = String::from_utf8(output).unwrap();
+        assert!(text.contains("every checksum matches"));
+        assert!(text.contains("   1. P1 draw") && text.contains("   3. "));
+        assert!(!text.contains("   4. "), "Stepping should stop at q.");
+        fs::remove_dir_all(&dir).unwrap();
+
+        let mut game = Game::new(&Board::new_base_game(2), true, Some(seed_bytes(1))).unwrap();
+        game.apply(&Move::new(0, MoveType::Draw)).unwrap();
+        let path = temp_path("save.json");
+        let sources = CardSources { sets: vec![Set::Base, Set::Dragons], files: vec![] };
+        save_game(&game, &sources, &path).unwrap();
+        let mut output = vec![];
+        run(Command::Inspect { save: path.clone() }, &CardSources::default(), &mut io::empty(), &mut output).unwrap();
+        fs::remove_file(&path).unwrap();
+
+        let text = String::from_utf8(output).unwrap();
+        assert!(text.contains(&format!("Save version {}, 1 moves played", crate::save::SAVE_VERSION)));
+        assert!(text.contains("Sets: Base, Dragons"));
+        assert!(text.contains("P2 hand: (empty)"));
+        assert!(text.contains("Waiting on P1: "));
+    }
+
+    #[test]
+    fn test_play_loaded_game() {
+        let board = Board::new_with_sets(&[Set::Base, Set::Dragons], 2);
+        let mut game = Game::new(&board, true, Some(seed_bytes(2))).unwrap();
+        game.apply(&Move::new(0, MoveType::Draw)).unwrap();
+        let sources = CardSources { sets: vec![Set::Base, Set::Dragons], files: vec![] };
+        let path = temp_path("loaded.json");
+        save_game(&game, &sources, &path).unwrap();
+
+        // P1 plays a Dragons card, then P2 saves back over the file the game came from.
+        let command = Command::Play { players: None, seed: None, tui: false, seats: vec![], load: Some(path.clone()) };
+        let mut output = vec![];
+        run(command, &CardSources::default(), &mut "\n1\n\nsave\n".as_bytes(), &mut output).unwrap();
+        let save = load_game(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+        assert!(String::from_utf8(output).unwrap().contains("Saved to"));
+        assert!(save.moves.len() == 2 && save.moves[0] == game.moves()[0]);
+        assert!(save.sources == sources);
+    }
+}
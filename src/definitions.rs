@@ -0,0 +1,318 @@
+// Cards are described by data rather than code. A definition file is a JSON list of cards:
+//
+//   [{ "name": "Unicorn Poison", "ctype": "Magic", "copies": 3, "expansion": "Base",
+//      "text": "DESTROY a Unicorn card.", "effect": { "Destroy": { "cards": "Unicorn" } } }, ...]
+//
+// Effects are written in the vocabulary of `effects::Effect` and choose cards with `targets::Selector`,
+// instants name their `counter` instead. Cards that change the rules while in a stable list their
+// `modifiers`, see `modifiers::Modifier`, and cards that step in when they would be removed their
+// `replacement`, see `effects::Replacement`.
+// `copies` defaults to one, everything but the name, type and expansion is optional.
+//
+// Baby unicorns make up the nursery, every other card goes into the deck. The base game is built in,
+// further files are loaded at startup and add to it, a card with a name that is already known
+// replaces the earlier definition. A card's `expansion` names the `Set` it comes with, games are
+// dealt from the cards of the sets chosen for them.
+
+// STD
+use std::collections::HashSet;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::str::FromStr;
+use std::sync::{OnceLock, RwLock};
+
+// EXT
+use serde::{Deserialize, Serialize};
+
+// UU
+use crate::cards::CardType;
+use crate::effects::{Effect, Replacement};
+use crate::modifiers::Continuous;
+use crate::state::History;
+
+// Card files built into the game, registered in this order.
+const BUILT_IN_CARDS: [&str; 4] = [
+    include_str!("../cards/base.json"),
+    include_str!("../cards/dragons.json"),
+    include_str!("../cards/rainbow_apocalypse.json"),
+    include_str!("../cards/unicorns_of_legend.json")
+];
+
+/// How an instant answers the chain.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum Counter {
+    // Cancels the card on top of the chain.
+    Neigh,
+    // A neigh nothing can answer.
+    SuperNeigh
+}
+
+impl Counter {
+    /// Whether this counter may answer the top of the chain in `history`.
+    pub fn answers(&self, history: &History) -> bool {
+        return history.last().is_some_and(|top| top.card.definition().counter != Some(Counter::SuperNeigh));
+    }
+}
+
+/// A box of cards, the base game or one of its expansions.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Set {
+    Base,
+    RainbowApocalypse,
+    Dragons,
+    UnicornsOfLegend,
+    Nsfw,
+    Nightmares,
+    Adventures
+}
+
+impl Set {
+    pub const ALL: [Set; 7] = [Set::Base, Set::RainbowApocalypse, Set::Dragons, Set::UnicornsOfLegend, Set::Nsfw, Set::Nightmares, Set::Adventures];
+
+    /// The `expansion` cards of this set give.
+    pub fn name(&self) -> &'static str {
+        return match self {
+            Set::Base => "Base",
+            Set::RainbowApocalypse => "Rainbow Apocalypse",
+            Set::Dragons => "Dragons",
+            Set::UnicornsOfLegend => "Unicorns of Legend",
+            Set::Nsfw => "NSFW",
+            Set::Nightmares => "Nightmares",
+            Set::Adventures => "Adventures"
+        };
+    }
+
+    /// Known definitions of this set.
+    pub fn cards(&self) -> Vec<&'static CardDef> {
+        return expansion(self.name());
+    }
+}
+
+impl fmt::Display for Set {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+impl FromStr for Set {
+    type Err = String;
+
+    /// Reads a set by name, ignoring case, spaces and dashes, e.g. `rainbow-apocalypse`.
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        let simple = |name: &str| name.chars().filter(|c| c.is_alphanumeric()).collect::<String>().to_lowercase();
+        return Set::ALL.into_iter().find(|set| simple(set.name()) == simple(text)).ok_or_else(|| {
+            let names: Vec<_> = Set::ALL.iter().map(|set| simple(set.name())).collect();
+            return format!("unknown card set {:?}, expected one of {}", text, names.join(", "));
+        });
+    }
+}
+
+fn one() -> usize {
+    return 1;
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct CardDef {
+    pub name: String,
+    pub ctype: CardType,
+    // Copies in a deck built from this definition.
+    #[serde(default = "one")]
+    pub copies: usize,
+    pub expansion: String,
+    // Rules text as printed on the card.
+    #[serde(default)]
+    pub text: String,
+    // Rainbow cards are picked out by the `Rainbow` filter.
+    #[serde(default)]
+    pub rainbow: bool,
+    // A stable holds at most one Legendary card.
+    #[serde(default)]
+    pub legendary: bool,
+    // Resolved when a magic card is played, or when any other card enters a stable.
+    #[serde(default)]
+    pub effect: Option<Effect>,
+    // Paid by the player before the effect resolves, a card whose cost cannot be paid cannot be played.
+    #[serde(default)]
+    pub cost: Option<Effect>,
+    #[serde(default)]
+    pub counter: Option<Counter>,
+    // Resolved by the owner once this card went from their stable to the discard pile.
+    #[serde(default)]
+    pub discarded: Option<Effect>,
+    // Resolved by the former owner once this card was stolen from their stable.
+    #[serde(default)]
+    pub leaves: Option<Effect>,
+    // Rule changes while this card is in a stable.
+    #[serde(default)]
+    pub modifiers: Vec<Continuous>,
+    // Steps in when this card, or one it guards, would be removed from its stable.
+    #[serde(default)]
+    pub replacement: Option<Replacement>
+}
+
+#[derive(Debug)]
+pub enum DefinitionError {
+    Io(io::Error),
+    Json(serde_json::Error),
+    // A definition that parses but makes no sense.
+    Invalid { card: String, reason: String }
+}
+
+impl fmt::Display for DefinitionError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DefinitionError::Io(e) => write!(f, "could not read card definitions: {}", e),
+            DefinitionError::Json(e) => write!(f, "malformed card definitions: {}", e),
+            DefinitionError::Invalid { card, reason } => write!(f, "card {:?} {}", card, reason)
+        }
+    }
+}
+
+impl std::error::Error for DefinitionError {}
+
+/// Reads and checks a list of definitions without registering them.
+pub fn parse(text: &str) -> Result<Vec<CardDef>, DefinitionError> {
+    let defs: Vec<CardDef> = serde_json::from_str(text).map_err(DefinitionError::Json)?;
+
+    let mut names = HashSet::new();
+    for def in defs.iter() {
+        let invalid = |reason: &str| DefinitionError::Invalid { card: def.name.clone(), reason: reason.to_string() };
+        if def.name.trim().is_empty() {
+            return Err(invalid("needs a name"));
+        }
+        if !names.insert(def.name.as_str()) {
+            return Err(invalid("is defined twice"));
+        }
+        if def.copies == 0 {
+            return Err(invalid("needs at least one copy"));
+        }
+        let instant = def.ctype == CardType::Instant;
+        if def.counter.is_some() != instant {
+            return Err(invalid(&format!("of type {:?} cannot have a counter", def.ctype)));
+        }
+        if instant && (def.effect.is_some() || def.cost.is_some()) {
+            return Err(invalid("is an instant, its counter is its effect"));
+        }
+        if (instant || def.ctype == CardType::Magic) && (def.discarded.is_some() || def.leaves.is_some() || !def.modifiers.is_empty() || def.replacement.is_some()) {
+            return Err(invalid(&format!("of type {:?} never sits in a stable", def.ctype)));
+        }
+        let triggers = def.effect.iter().chain(def.cost.iter()).chain(def.discarded.iter()).chain(def.leaves.iter());
+        for effect in triggers.chain(def.replacement.iter().map(|r| &r.instead)) {
+            effect.check().map_err(|reason| invalid(&reason))?;
+        }
+    }
+    return Ok(defs);
+}
+
+// Every known definition in the order it was first registered, which is the order decks are built in.
+// Definitions live for the rest of the program so cards can hand out their names as `&'static str`.
+static DEFINITIONS: OnceLock<RwLock<Vec<&'static CardDef>>> = OnceLock::new();
+
+fn registry() -> &'static RwLock<Vec<&'static CardDef>> {
+    return DEFINITIONS.get_or_init(|| {
+        let defs = BUILT_IN_CARDS.iter().flat_map(|text| parse(text).expect("built in card definitions should be valid"));
+        return RwLock::new(defs.map(|d| &*Box::leak(Box::new(d))).collect());
+    });
+}
+
+/// Adds `defs` to the known cards, replacing any known card of the same name.
+pub fn register(defs: Vec<CardDef>) -> Vec<&'static CardDef> {
+    let mut known = registry().write().unwrap();
+    let mut added = vec![];
+    for def in defs {
+        let def: &'static CardDef = Box::leak(Box::new(def));
+        match known.iter().position(|k| k.name == def.name) {
+            Some(idx) => known[idx] = def,
+            None => known.push(def)
+        }
+        added.push(def);
+    }
+    return added;
+}
+
+/// Reads and checks the definitions in the file at `path` without registering them.
+pub fn read_file(path: &Path) -> Result<Vec<CardDef>, DefinitionError> {
+    let text = fs::read_to_string(path).map_err(DefinitionError::Io)?;
+    return parse(&text);
+}
+
+/// Parses and registers the definitions in the file at `path`.
+pub fn load_file(path: &Path) -> Result<Vec<&'static CardDef>, DefinitionError> {
+    return Ok(register(read_file(path)?));
+}
+
+pub fn definitions() -> Vec<&'static CardDef> {
+    return registry().read().unwrap().clone();
+}
+
+pub fn find(name: &str) -> Option<&'static CardDef> {
+    return registry().read().unwrap().iter().find(|d| d.name == name).cloned();
+}
+
+/// Known definitions belonging to `expansion`.
+pub fn expansion(expansion: &str) -> Vec<&'static CardDef> {
+    return definitions().into_iter().filter(|d| d.expansion == expansion).collect();
+}
+
+#[cfg(test)]
+mod DefinitionsTest {
+    use super::*;
+    use crate::targets::{Filter, Selector};
+
+    #[test]
+    fn test_base_definitions() {
+        let base = expansion("Base");
+        let names: Vec<_> = base.iter().map(|d| d.name.as_str()).collect();
+        assert!(names == vec!["Basic Unicorn", "Baby Unicorn", "Neigh", "Super Neigh", "Unicorn Phoenix", "Black Knight Unicorn", "Stabby the Unicorn", "Seductive Unicorn",
+                            "Unicorn Poison", "Two-For-One", "Rainbow Aura", "Double Dutch"]);
+
+        let poison = find("Unicorn Poison").unwrap();
+        assert!(poison.copies == 3 && poison.ctype == CardType::Magic);
+        assert!(poison.effect == Some(Effect::Destroy(Selector::from(Filter::Unicorn))) && poison.text == "DESTROY a Unicorn card.");
+        assert!(find("Super Neigh").unwrap().counter == Some(Counter::SuperNeigh));
+    }
+
+    #[test]
+    fn test_sets() {
+        assert!(Set::from_str("rainbow-apocalypse") == Ok(Set::RainbowApocalypse));
+        assert!(Set::from_str("Unicorns of Legend") == Ok(Set::UnicornsOfLegend));
+        assert!(Set::from_str("NSFW") == Ok(Set::Nsfw) && Set::Base.to_string() == "Base");
+        assert!(Set::from_str("dragon").unwrap_err().starts_with("unknown card set \"dragon\""));
+        assert!(Set::Base.cards() == expansion("Base") && Set::Dragons.cards().len() == 8);
+        assert!(Set::RainbowApocalypse.cards().iter().filter(|d| d.rainbow).count() == 5);
+        assert!(Set::UnicornsOfLegend.cards().iter().filter(|d| d.legendary).count() == 6);
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        let defs = parse(r#"[{ "name": "Glitter Bomb", "ctype": "Upgrade", "expansion": "Homebrew" }]"#).unwrap();
+        assert!(defs[0].copies == 1 && defs[0].effect.is_none() && defs[0].text.is_empty());
+
+        assert!(matches!(parse(r#"[{ "name": "Neigh" }]"#), Err(DefinitionError::Json(_))));
+        let twice = r#"[{ "name": "A", "ctype": "Magic", "expansion": "X" }, { "name": "A", "ctype": "Magic", "expansion": "X" }]"#;
+        assert!(matches!(parse(twice), Err(DefinitionError::Invalid { card, .. }) if card == "A"));
+        let misfit = r#"[{ "name": "Odd Neigh", "ctype": "Magic", "expansion": "X", "counter": "Neigh" }]"#;
+        assert!(parse(misfit).unwrap_err().to_string() == "card \"Odd Neigh\" of type Magic cannot have a counter");
+        let unknown = r#"[{ "name": "Odd Poison", "ctype": "Magic", "expansion": "X", "effect": { "Explode": "Unicorn" } }]"#;
+        assert!(matches!(parse(unknown), Err(DefinitionError::Json(_))));
+        let blind = r#"[{ "name": "Blind Poison", "ctype": "Magic", "expansion": "X", "effect": { "Destroy": { "zone": "Hand" } } }]"#;
+        assert!(parse(blind).unwrap_err().to_string() == "card \"Blind Poison\" cannot choose from the Hand zone");
+    }
+
+    #[test]
+    fn test_read_file() {
+        let path = std::env::temp_dir().join(format!("uu_definitions_test_{}.json", std::process::id()));
+        let text = r#"[{ "name": "Rainbow Poison", "ctype": "Magic", "copies": 2, "expansion": "Homebrew",
+                         "text": "DESTROY a Unicorn card.", "effect": { "Destroy": { "cards": "Unicorn" } } }]"#;
+        fs::write(&path, text).unwrap();
+        let read = read_file(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+
+        assert!(read.len() == 1 && read[0].name == "Rainbow Poison" && read[0].copies == 2);
+        assert!(find("Rainbow Poison").is_none() && expansion("Homebrew").is_empty(), "Reading registers nothing.");
+        assert!(matches!(read_file(&path), Err(DefinitionError::Io(_))));
+        assert!(matches!(load_file(&path), Err(DefinitionError::Io(_))));
+    }
+}
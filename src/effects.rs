@@ -0,0 +1,868 @@
+// Card abilities are written as small effects that combine, e.g. "sacrifice a card, then draw two" is
+//
+//   { "Sequence": [{ "Sacrifice": {} }, { "Draw": 2 }] }
+//
+// Effects that choose cards describe them with a `targets::Selector`. Sacrifice always picks from
+// your own stable and Steal from someone else's, Search looks through the deck or discard pile and
+// Bring through your own hand, for a card that enters your stable without being played.
+//
+// Effects resolve from a stack of pending frames on the `GameState`, one frame per ability being
+// resolved. Steps that need no input are applied straight away. A step with a choice stops resolving
+// and asks the frame's controller through `react_metadata`; `choices` lists the moves answering it
+// and `resolve` applies one, so every choice is a branch of the game graph. Frames of optional
+// effects are asked in the Effect phase and may be passed on until their first choice is made, all
+// other choices are asked in the React phase.
+//
+// A card about to be destroyed, sacrificed or returned to a hand first goes past the replacements of
+// its stable, e.g.
+// "if this card would be destroyed, you may DISCARD a card instead" is
+//
+//   { "guards": "This", "on": ["Destroy"], "instead": { "Discard": { "count": 1 } } }
+//
+// A mandatory replacement applies straight away. Optional ones hold the card back in a removal frame
+// where its owner picks one of them or passes, which lets the removal go ahead.
+
+// EXT
+use serde::{Deserialize, Serialize};
+
+// UU
+use crate::cards::Card;
+use crate::modifiers;
+use crate::moves::{Move, MoveType};
+use crate::state::{Action, ActionType, GameState, LogicError, PhaseType, ReactMetadata, ResponseOp, Zone};
+use crate::targets::{Area, Filter, Owner, Selector};
+
+/// Players an effect applies to, relative to the player resolving it.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub enum Who {
+    #[default]
+    You,
+    Others,
+    All
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum Effect {
+    // Move a selected card from a stable to the discard pile.
+    Destroy(Selector),
+    // Move a selected card from your own stable to the discard pile.
+    Sacrifice(Selector),
+    // Move a selected card from another player's stable into yours.
+    Steal(Selector),
+    Draw(usize),
+    Discard { count: usize, #[serde(default)] who: Who },
+    // Move a selected card from a stable to its owner's hand.
+    ReturnToHand(Selector),
+    // Take a selected card from the deck or discard pile into your hand.
+    Search(Selector),
+    // Move a selected Unicorn, Upgrade, Downgrade or Dragon card from your hand into your stable.
+    Bring(Selector),
+    // Put this card back into its owner's stable from the discard pile.
+    Revive,
+    // Move this card from its stable to the discard pile.
+    SacrificeThis,
+    // Move this card from wherever it is to the nursery.
+    ReturnToNursery,
+    Sequence(Vec<Effect>),
+    May(Box<Effect>),
+    ForEachPlayer(Box<Effect>)
+}
+
+impl Effect {
+    // Kind of answer this step waits for, `None` for steps that resolve on their own.
+    fn decision(&self) -> Option<ResponseOp> {
+        return match self {
+            Effect::Destroy(_) => Some(ResponseOp::Destroy),
+            Effect::Sacrifice(_) => Some(ResponseOp::Sacrifice),
+            Effect::Steal(_) => Some(ResponseOp::Steal),
+            Effect::ReturnToHand(_) => Some(ResponseOp::Return),
+            Effect::Search(_) => Some(ResponseOp::Search),
+            Effect::Bring(_) => Some(ResponseOp::Bring),
+            Effect::Discard { who: Who::You, .. } => Some(ResponseOp::Discard),
+            _ => None
+        };
+    }
+
+    // Cards this step chooses from, with the owner its kind of choice implies.
+    fn selector(&self) -> Option<Selector> {
+        return match self {
+            Effect::Destroy(selector) | Effect::ReturnToHand(selector) | Effect::Search(selector) => Some(selector.clone()),
+            Effect::Sacrifice(selector) | Effect::Bring(selector) => Some(Selector { owner: Owner::You, ..selector.clone() }),
+            Effect::Steal(selector) => Some(Selector { owner: Owner::Other, ..selector.clone() }),
+            _ => None
+        };
+    }
+
+    /// Describes why this effect could never resolve, if so.
+    pub fn check(&self) -> Result<(), String> {
+        let zone = |selector: &Selector, zones: &[Area]| -> Result<(), String> {
+            if zones.contains(&selector.zone) {
+                return Ok(());
+            }
+            return Err(format!("cannot choose from the {:?} zone", selector.zone));
+        };
+        return match self {
+            Effect::Destroy(selector) | Effect::ReturnToHand(selector) => zone(selector, &[Area::Stable]),
+            Effect::Sacrifice(selector) | Effect::Steal(selector) | Effect::Bring(selector) => {
+                let area = if let Effect::Bring(_) = self { Area::Hand } else { Area::Stable };
+                zone(selector, &[area])?;
+                if selector.owner != Owner::Any {
+                    return Err("cannot choose whose stable to take from".to_string());
+                }
+                return Ok(());
+            },
+            Effect::Search(selector) => zone(selector, &[Area::Deck, Area::Discard]),
+            Effect::Sequence(steps) => steps.iter().try_for_each(|s| s.check()),
+            Effect::May(effect) | Effect::ForEachPlayer(effect) => effect.check(),
+            _ => Ok(())
+        };
+    }
+
+    /// Whether `player` could start resolving this effect, cards whose effect cannot start are not playable.
+    pub fn can_start(&self, state: &GameState, player: usize) -> bool {
+        return match self {
+            Effect::Sequence(steps) => steps.first().is_none_or(|s| s.can_start(state, player)),
+            step if step.decision().is_some() => !options(state, player, None, step).is_empty(),
+            _ => true
+        };
+    }
+}
+
+/// Ways a card can be made to leave its stable against the owner's will.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum Removal {
+    Destroy,
+    Sacrifice,
+    // Back to the owner's hand.
+    Return
+}
+
+impl Removal {
+    fn action(&self) -> ActionType {
+        return match self {
+            Removal::Destroy => ActionType::Destroy,
+            Removal::Sacrifice => ActionType::Sacrifice,
+            Removal::Return => ActionType::Return
+        };
+    }
+}
+
+/// Cards a replacement looks after.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum Guard {
+    // The card with the replacement.
+    This,
+    // Other matching cards in the same stable.
+    Stable(Filter)
+}
+
+/// Something a card does instead of letting a guarded card be removed.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Replacement {
+    pub guards: Guard,
+    pub on: Vec<Removal>,
+    // Resolved by the owner in place of the removal.
+    pub instead: Effect,
+    // Applies without asking.
+    #[serde(default)]
+    pub mandatory: bool
+}
+
+/// One ability being resolved.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Pending {
+    // Player making the choices.
+    pub controller: usize,
+    // Card the ability belongs to.
+    pub source: Box<dyn Card>,
+    // Steps left, resolved front to back.
+    pub steps: Vec<Effect>,
+    // Can still be declined as a whole, until its first choice is made.
+    pub optional: bool,
+    // Where the source card is, kept up to date while cards move and cleared once it leaves that zone.
+    #[serde(default)]
+    pub this: Option<(Zone, usize)>,
+    // Set on frames holding back the removal of their source until its owner decides on replacements.
+    #[serde(default)]
+    pub removal: Option<Removal>
+}
+
+impl Pending {
+    pub fn new(controller: usize, source: Box<dyn Card>, effect: &Effect) -> Self {
+        return Pending { controller, source, steps: vec![effect.clone()], optional: false, this: None, removal: None };
+    }
+
+    /// The same frame, with its source card at `index` of `zone`.
+    pub fn at(self, zone: Zone, index: usize) -> Self {
+        return Pending { this: Some((zone, index)), ..self };
+    }
+}
+
+// Takes the card at `index` of `zone`, keeping the positions pending frames remember in step.
+fn take(state: &mut GameState, zone: &Zone, index: usize) -> Result<Box<dyn Card>, LogicError> {
+    let cards = state.board.cards_mut(zone);
+    if index >= cards.len() {
+        return Err(LogicError::CardNotInZone { zone: zone.clone(), index });
+    }
+    let card = cards.remove(index);
+    for frame in state.pending.iter_mut() {
+        frame.this = match frame.this.take() {
+            Some((z, idx)) if z == *zone && idx == index => None,
+            Some((z, idx)) if z == *zone && idx > index => Some((z, idx - 1)),
+            this => this
+        };
+    }
+    return Ok(card);
+}
+
+// Replacements in `player`'s stable that could step in for `removal` of the card at `index`, with
+// the position of the card offering each.
+fn offers(state: &GameState, removal: &Removal, player: usize, index: usize) -> Vec<(usize, &'static Replacement)> {
+    let zone = Zone::Stable(player);
+    let stable = &state.board.players[player].stable;
+    let Some(target) = stable.get(index) else {
+        return vec![];
+    };
+
+    let mut offers = vec![];
+    for (r_idx, card) in stable.iter().enumerate() {
+        let Some(replacement) = &card.definition().replacement else {
+            continue;
+        };
+        let guarded = match &replacement.guards {
+            Guard::This => r_idx == index,
+            Guard::Stable(filter) => r_idx != index && filter.matches(&state.board, &zone, target.as_ref())
+        };
+        if guarded && replacement.on.contains(removal) && (replacement.mandatory || replacement.instead.can_start(state, player)) {
+            offers.push((r_idx, replacement));
+        }
+    }
+    return offers;
+}
+
+// Sends the card at `index` of `player`'s stable where `removal` takes it, returning it, unless a
+// replacement steps in. Optional replacements are only offered when `offer` is set.
+fn remove(state: &mut GameState, removal: Removal, player: usize, index: usize, offer: bool) -> Result<Option<Box<dyn Card>>, LogicError> {
+    let zone = Zone::Stable(player);
+    let offers = offers(state, &removal, player, index);
+    if let Some((r_idx, replacement)) = offers.iter().find(|(_, r)| r.mandatory) {
+        let card = state.board.players[player].stable[*r_idx].clone();
+        state.pending.push(Pending::new(player, card, &replacement.instead).at(zone, *r_idx));
+        return Ok(None);
+    }
+    if offer && !offers.is_empty() {
+        let source = state.board.players[player].stable[index].clone();
+        let held = Pending { controller: player, source, steps: vec![], optional: true, this: Some((zone, index)), removal: Some(removal) };
+        state.pending.push(held);
+        return Ok(None);
+    }
+
+    let card = take(state, &zone, index)?;
+    if removal == Removal::Return {
+        state.board.players[player].hand.push(card.clone());
+        return Ok(Some(card));
+    }
+    state.board.discard.push(card.clone());
+    if let Some(effect) = &card.definition().discarded {
+        // Abilities of the card just moved resolve before the rest of the effect.
+        state.pending.push(Pending::new(player, card.clone(), effect).at(Zone::Discard, state.board.discard.len() - 1));
+    }
+    return Ok(Some(card));
+}
+
+// Puts `card` into `player`'s stable and its `effect` on the stack, returning where it went.
+fn enter(state: &mut GameState, player: usize, card: Box<dyn Card>) -> (Zone, usize) {
+    state.board.players[player].stable.push(card.clone());
+    let at = (Zone::Stable(player), state.board.players[player].stable.len() - 1);
+    if let Some(effect) = &card.definition().effect {
+        state.pending.push(Pending::new(player, card, effect).at(at.0.clone(), at.1));
+    }
+    return at;
+}
+
+// Moves the card at `index` of `from`'s stable into `to`'s. The card leaves one stable and enters the
+// other, so its `leaves` ability resolves for its old owner and then its `effect` for the new one.
+fn steal(state: &mut GameState, from: usize, index: usize, to: usize) -> Result<Box<dyn Card>, LogicError> {
+    let card = take(state, &Zone::Stable(from), index)?;
+    let at = enter(state, to, card.clone());
+    if let Some(effect) = &card.definition().leaves {
+        state.pending.push(Pending::new(from, card.clone(), effect).at(at.0, at.1));
+    }
+    return Ok(card);
+}
+
+// Every player in turn order, starting with `player`.
+fn from_player(state: &GameState, player: usize) -> Vec<usize> {
+    let count = state.board.players.len();
+    return (0..count).map(|offset| (player + offset) % count).collect();
+}
+
+// Moves answering `step` for `player`, `this` being where the effect's own card is.
+fn options(state: &GameState, player: usize, this: Option<&(Zone, usize)>, step: &Effect) -> Vec<MoveType> {
+    if let Effect::Discard { .. } = step {
+        return (0..state.board.players[player].hand.len()).map(MoveType::Discard).collect();
+    }
+    let Some(selector) = step.selector() else {
+        return vec![];
+    };
+    let targets = selector.targets(&state.board, player, this).into_iter().filter(|(zone, idx)| !shielded(state, step, zone, *idx));
+    return targets.map(|(zone, idx)| match zone {
+        Zone::Stable(p_idx) => MoveType::Target(p_idx, idx),
+        Zone::Hand(_) => MoveType::Play(idx),
+        _ => MoveType::Search(idx)
+    }).collect();
+}
+
+/// Checks that the target `mv` chooses is one the top pending effect may choose.
+pub fn validate_choice(state: &GameState, mv: &Move) -> Result<(), LogicError> {
+    let frame = state.pending.last().ok_or(LogicError::NoPendingAction)?;
+    let step = frame.steps.first().ok_or(LogicError::NoPendingAction)?;
+    let Some(selector) = step.selector() else {
+        return Err(LogicError::IllegalMove(mv.clone()));
+    };
+    let (zone, index) = match (&mv.mtype, &selector.zone) {
+        (MoveType::Target(p_idx, s_idx), Area::Stable) => {
+            if *p_idx >= state.board.players.len() {
+                return Err(LogicError::UnknownPlayer(*p_idx));
+            }
+            (Zone::Stable(*p_idx), *s_idx)
+        },
+        (MoveType::Search(idx), Area::Deck) => (Zone::Deck, *idx),
+        (MoveType::Search(idx), Area::Discard) => (Zone::Discard, *idx),
+        (MoveType::Play(idx), Area::Hand) => (Zone::Hand(frame.controller), *idx),
+        _ => return Err(LogicError::IllegalMove(mv.clone()))
+    };
+    selector.validate(&state.board, frame.controller, frame.this.as_ref(), &zone, index)?;
+    return match (shielded(state, step, &zone, index), &zone) {
+        (true, Zone::Stable(player)) => Err(LogicError::InvalidTarget { player: *player, index }),
+        (true, _) => Err(LogicError::InvalidChoice { zone, index }),
+        _ => Ok(())
+    };
+}
+
+// Whether `step` cannot choose the card at `index` of `zone` even though its selector matches, be it
+// kept safe by a card in play or unable to go where the step takes it.
+fn shielded(state: &GameState, step: &Effect, zone: &Zone, index: usize) -> bool {
+    if let (Effect::Bring(_), Zone::Hand(player)) = (step, zone) {
+        let card = state.board.cards(zone).get(index);
+        return card.is_none_or(|c| !c.is_permanent() || !modifiers::can_enter(&state.board, *player, c.as_ref()));
+    }
+    let Zone::Stable(player) = zone else {
+        return false;
+    };
+    return match step {
+        Effect::Destroy(_) => modifiers::cannot_be_destroyed(&state.board, *player, index),
+        Effect::Steal(_) => modifiers::cannot_be_stolen(&state.board, *player, index),
+        _ => false
+    };
+}
+
+fn ask(state: &mut GameState, follow_up: ResponseOp, controller: usize, optional: bool) {
+    state.phase = if optional { PhaseType::Effect } else { PhaseType::React };
+    state.react_metadata = Some(ReactMetadata { follow_up, response_user: vec![controller] });
+}
+
+/// Resolves pending effects until one needs a choice or none are left.
+pub fn advance(mut state: GameState) -> Result<GameState, LogicError> {
+    loop {
+        let Some(frame) = state.pending.last().cloned() else {
+            return Ok(state);
+        };
+        if frame.removal.is_some() {
+            ask(&mut state, ResponseOp::Replace, frame.controller, true);
+            return Ok(state);
+        }
+        let Some(step) = frame.steps.first().cloned() else {
+            state.pending.pop();
+            continue;
+        };
+
+        let top = state.pending.len() - 1;
+        let (controller, source) = (frame.controller, frame.source.clone());
+        match step {
+            Effect::Sequence(steps) => {
+                state.pending[top].steps.splice(0..1, steps);
+            },
+            Effect::May(effect) => {
+                state.pending[top].steps.remove(0);
+                state.pending.push(Pending { steps: vec![*effect], optional: true, ..frame });
+            },
+            // Optional steps without a choice of their own need a yes or no first.
+            step if frame.optional && step.decision().is_none() => {
+                ask(&mut state, ResponseOp::Confirm, controller, true);
+                return Ok(state);
+            },
+            Effect::ForEachPlayer(_) | Effect::Discard { .. } if step.decision().is_none() => {
+                state.pending[top].steps.remove(0);
+                let (players, effect) = match step {
+                    Effect::ForEachPlayer(effect) => (from_player(&state, controller), *effect),
+                    Effect::Discard { count, who } => {
+                        let mut players = from_player(&state, controller);
+                        if who == Who::Others {
+                            players.remove(0);
+                        }
+                        (players, Effect::Discard { count, who: Who::You })
+                    },
+                    _ => unreachable!()
+                };
+                // The first player in turn order resolves first, so goes on top.
+                for player in players.into_iter().rev() {
+                    state.pending.push(Pending { this: frame.this.clone(), ..Pending::new(player, source.clone(), &effect) });
+                }
+            },
+            Effect::Draw(count) => {
+                state.pending[top].steps.remove(0);
+                for _ in 0..count {
+                    let Some(card) = state.board.deck.pop() else {
+                        break;
+                    };
+                    state.board.players[controller].hand.push(card);
+                }
+            },
+            Effect::SacrificeThis => {
+                state.pending[top].steps.remove(0);
+                if let Some((Zone::Stable(player), idx)) = frame.this {
+                    remove(&mut state, Removal::Sacrifice, player, idx, true)?;
+                }
+            },
+            Effect::ReturnToNursery => {
+                state.pending[top].steps.remove(0);
+                if let Some((zone, idx)) = frame.this {
+                    let card = take(&mut state, &zone, idx)?;
+                    state.board.nursery.push(card);
+                }
+            },
+            Effect::Revive => {
+                state.pending[top].steps.remove(0);
+                if let Some((Zone::Discard, idx)) = frame.this {
+                    let card = take(&mut state, &Zone::Discard, idx)?;
+                    state.board.players[controller].stable.push(card);
+                }
+            },
+            step => {
+                if options(&state, controller, frame.this.as_ref(), &step).is_empty() {
+                    // An optional ability that cannot be done is dropped, a mandatory one does what it can.
+                    if frame.optional {
+                        state.pending.pop();
+                    } else {
+                        state.pending[top].steps.remove(0);
+                    }
+                    continue;
+                }
+                ask(&mut state, step.decision().unwrap(), controller, frame.optional);
+                return Ok(state);
+            }
+        }
+    }
+}
+
+/// Moves answering the choice pending effects wait on.
+pub fn choices(state: &GameState) -> Vec<MoveType> {
+    let (Some(frame), Some(meta)) = (state.pending.last(), &state.react_metadata) else {
+        return vec![];
+    };
+    if let (Some(removal), Some((Zone::Stable(player), idx))) = (&frame.removal, &frame.this) {
+        return offers(state, removal, *player, *idx).into_iter().map(|(r_idx, _)| MoveType::Target(*player, r_idx)).collect();
+    }
+    let Some(step) = frame.steps.first() else {
+        return vec![];
+    };
+
+    if meta.follow_up == ResponseOp::Confirm {
+        return vec![MoveType::Accept];
+    }
+    return options(state, frame.controller, frame.this.as_ref(), step);
+}
+
+/// Applies the choice `mv` to the top pending effect, leaving the rest to `advance`.
+pub fn resolve(state: &GameState, mv: &Move) -> Result<(Action, GameState), LogicError> {
+    let mut next = state.clone();
+    let frame = next.pending.pop().ok_or(LogicError::NoPendingAction)?;
+    if let Some(removal) = frame.removal.clone() {
+        return replace(next, frame, removal, mv);
+    }
+    let step = frame.steps.first().cloned().ok_or(LogicError::NoPendingAction)?;
+    let source = frame.source.clone();
+    let no_op = Action { card: source.clone(), atype: ActionType::NoOp, board: state.board.clone() };
+
+    if mv.mtype == MoveType::Pass {
+        // Declining drops the whole ability.
+        return Ok((no_op, next));
+    }
+
+    let mut rest = Pending { optional: false, ..frame };
+    rest.steps.remove(0);
+    let controller = rest.controller;
+    // Back on the stack first, so the position of its card follows the moves below.
+    next.pending.push(rest);
+    let (card, atype) = match (&step, &mv.mtype) {
+        (_, MoveType::Accept) => {
+            // Put the step back, it resolves now that it is no longer optional.
+            next.pending.last_mut().unwrap().steps.insert(0, step.clone());
+            (source, ActionType::NoOp)
+        },
+        (Effect::Destroy(_) | Effect::Sacrifice(_) | Effect::ReturnToHand(_), MoveType::Target(p_idx, s_idx)) => {
+            let removal = match step {
+                Effect::Destroy(_) => Removal::Destroy,
+                Effect::Sacrifice(_) => Removal::Sacrifice,
+                _ => Removal::Return
+            };
+            let target = next.board.cards(&Zone::Stable(*p_idx)).get(*s_idx).cloned()
+                                   .ok_or(LogicError::CardNotInZone { zone: Zone::Stable(*p_idx), index: *s_idx })?;
+            // A card held back by a replacement has not gone anywhere yet.
+            match remove(&mut next, removal.clone(), *p_idx, *s_idx, true)? {
+                Some(card) => (card, removal.action()),
+                None => (target, ActionType::NoOp)
+            }
+        },
+        (Effect::Steal(_), MoveType::Target(p_idx, s_idx)) => {
+            (steal(&mut next, *p_idx, *s_idx, controller)?, ActionType::Steal)
+        },
+        (Effect::Discard { count, who }, MoveType::Discard(h_idx)) => {
+            let card = take(&mut next, &Zone::Hand(controller), *h_idx)?;
+            next.board.discard.push(card.clone());
+            if *count > 1 {
+                next.pending.last_mut().unwrap().steps.insert(0, Effect::Discard { count: count - 1, who: who.clone() });
+            }
+            (card, ActionType::Discard)
+        },
+        (Effect::Search(selector), MoveType::Search(idx)) => {
+            let zone = if selector.zone == Area::Discard { Zone::Discard } else { Zone::Deck };
+            let card = take(&mut next, &zone, *idx)?;
+            next.board.players[controller].hand.push(card.clone());
+            (card, ActionType::Search)
+        },
+        (Effect::Bring(_), MoveType::Play(h_idx)) => {
+            let card = take(&mut next, &Zone::Hand(controller), *h_idx)?;
+            enter(&mut next, controller, card.clone());
+            (card, ActionType::Place)
+        },
+        _ => return Err(LogicError::IllegalMove(mv.clone()))
+    };
+
+    let action = Action { card, atype, board: next.board.clone() };
+    return Ok((action, next));
+}
+
+// Answers a removal frame: passing lets the removal go ahead, a target picks the replacement offered
+// by the card at that position.
+fn replace(mut next: GameState, frame: Pending, removal: Removal, mv: &Move) -> Result<(Action, GameState), LogicError> {
+    let Some((Zone::Stable(player), index)) = frame.this else {
+        return Err(LogicError::NoPendingAction);
+    };
+    let (card, atype) = match mv.mtype {
+        MoveType::Pass => match remove(&mut next, removal.clone(), player, index, false)? {
+            Some(card) => (card, removal.action()),
+            None => (frame.source, ActionType::NoOp)
+        },
+        MoveType::Target(p_idx, r_idx) if p_idx == player => {
+            let offered = offers(&next, &removal, player, index).into_iter().find(|(idx, _)| *idx == r_idx);
+            let Some((_, replacement)) = offered else {
+                return Err(LogicError::InvalidTarget { player: p_idx, index: r_idx });
+            };
+            let card = next.board.players[player].stable[r_idx].clone();
+            next.pending.push(Pending::new(player, card.clone(), &replacement.instead).at(Zone::Stable(player), r_idx));
+            (card, ActionType::NoOp)
+        },
+        _ => return Err(LogicError::IllegalMove(mv.clone()))
+    };
+    let action = Action { card, atype, board: next.board.clone() };
+    return Ok((action, next));
+}
+
+#[cfg(test)]
+mod EffectsTest {
+    use super::*;
+    use crate::cards::*;
+    use crate::definitions::Set;
+    use crate::game::{branches_after, Game};
+    use crate::moves::legal_actions;
+    use crate::state::*;
+    use crate::targets::Filter;
+
+    fn card(name: &str) -> Box<dyn Card> {
+        return card_from_name(name).unwrap();
+    }
+
+    fn resolving(board: &Board, controller: usize, effect: Effect) -> GameState {
+        let mut state = GameState::new(board, &PhaseType::Play);
+        state.pending = vec![Pending::new(controller, card("Unicorn Poison"), &effect)];
+        return advance(state).unwrap();
+    }
+
+    #[test]
+    fn test_sequence_and_draw() {
+        let mut board = Board::new_base_game(2);
+        board.players[0].stable = vec![card("Basic Unicorn"), card("Neigh")];
+        let deck_size = board.deck.len();
+
+        let effect = Effect::Sequence(vec![Effect::Sacrifice(Selector::from(Filter::Unicorn)), Effect::Draw(2)]);
+        let state = resolving(&board, 0, effect);
+        assert!(state.phase == PhaseType::React && state.react_metadata.as_ref().unwrap().follow_up == ResponseOp::Sacrifice);
+        assert!(choices(&state) == vec![MoveType::Target(0, 0)]);
+
+        let (action, next) = resolve(&state, &Move::new(0, MoveType::Target(0, 0))).unwrap();
+        assert!(action.atype == ActionType::Sacrifice && action.card.name() == "Basic Unicorn");
+        let next = advance(next).unwrap();
+        assert!(next.pending.is_empty());
+        assert!(next.board.players[0].hand.len() == 2 && next.board.deck.len() == deck_size - 2);
+        assert!(next.board.discard.has_card("Basic Unicorn"));
+    }
+
+    #[test]
+    fn test_may_and_for_each_player() {
+        let mut board = Board::new_base_game(3);
+        board.players[0].hand = vec![card("Neigh")];
+        board.players[2].hand = vec![card("Super Neigh"), card("Basic Unicorn")];
+
+        // Player two has nothing to discard and is skipped.
+        let state = resolving(&board, 2, Effect::ForEachPlayer(Box::new(Effect::Discard { count: 1, who: Who::You })));
+        assert!(state.react_metadata.as_ref().unwrap().response_user == vec![2]);
+        assert!(choices(&state) == vec![MoveType::Discard(0), MoveType::Discard(1)]);
+        let state = advance(resolve(&state, &Move::new(2, MoveType::Discard(1))).unwrap().1).unwrap();
+        assert!(state.react_metadata.as_ref().unwrap().response_user == vec![0]);
+        let state = advance(resolve(&state, &Move::new(0, MoveType::Discard(0))).unwrap().1).unwrap();
+        assert!(state.pending.is_empty() && state.board.discard.len() == 2);
+
+        // Optional effects without a choice ask first, and can be declined.
+        let state = resolving(&board, 0, Effect::May(Box::new(Effect::Draw(1))));
+        assert!(state.phase == PhaseType::Effect && choices(&state) == vec![MoveType::Accept]);
+        let declined = advance(resolve(&state, &Move::new(0, MoveType::Pass)).unwrap().1).unwrap();
+        assert!(declined.pending.is_empty() && declined.board.players[0].hand.len() == 1);
+        let accepted = advance(resolve(&state, &Move::new(0, MoveType::Accept)).unwrap().1).unwrap();
+        assert!(accepted.pending.is_empty() && accepted.board.players[0].hand.len() == 2);
+    }
+
+    #[test]
+    fn test_steal_return_and_search() {
+        let mut board = Board::new_base_game(2);
+        board.players[0].stable = vec![card("Basic Unicorn")];
+        board.players[1].stable = vec![card("Unicorn Phoenix")];
+
+        let state = resolving(&board, 0, Effect::Steal(Selector::from(Filter::Unicorn)));
+        assert!(choices(&state) == vec![MoveType::Target(1, 0)], "Only other stables can be stolen from.");
+        let (_, next) = resolve(&state, &Move::new(0, MoveType::Target(1, 0))).unwrap();
+        assert!(next.board.players[0].stable.has_card("Unicorn Phoenix") && next.board.players[1].stable.is_empty());
+
+        let state = resolving(&board, 1, Effect::ReturnToHand(Selector::from(Filter::Named("Basic Unicorn".to_string()))));
+        let (_, next) = resolve(&state, &Move::new(1, MoveType::Target(0, 0))).unwrap();
+        assert!(next.board.players[0].hand.has_card("Basic Unicorn"));
+
+        let state = resolving(&board, 1, Effect::Search(Selector { zone: Area::Deck, ..Selector::from(Filter::Type(CardType::Instant)) }));
+        assert!(choices(&state).len() == 4, "Three Neighs and a Super Neigh.");
+        let MoveType::Search(d_idx) = choices(&state)[0] else {
+            panic!("Searching should pick from the deck.");
+        };
+        let (action, next) = resolve(&state, &Move::new(1, MoveType::Search(d_idx))).unwrap();
+        assert!(next.board.players[1].hand.len() == 1 && action.card.ctype() == CardType::Instant);
+        assert!(next.board.deck.len() == board.deck.len() - 1);
+    }
+
+    #[test]
+    fn test_any_card_skips_dragons() {
+        let mut board = Board::new_base_game(2);
+        board.players[0].stable = vec![card("Red Dragon")];
+        board.players[1].stable = vec![card("Gold Dragon"), card("Basic Unicorn")];
+
+        // Two-For-One cannot be paid for with a Dragon, nor destroy one.
+        let two_for_one = card("Two-For-One");
+        let state = GameState::new(&board, &PhaseType::Play);
+        assert!(!crate::moves::playable(&state, 0, two_for_one.as_ref()));
+        let state = resolving(&board, 0, two_for_one.definition().effect.clone().unwrap());
+        assert!(choices(&state) == vec![MoveType::Target(1, 1)]);
+        let err = validate_choice(&state, &Move::new(0, MoveType::Target(1, 0)));
+        assert!(matches!(err, Err(LogicError::InvalidTarget { player: 1, index: 0 })));
+    }
+
+    #[test]
+    fn test_bring() {
+        let mut board = Board::new_base_game(2);
+        board.players[0].hand = vec![card("Neigh"), card("Seductive Unicorn"), card("Rainbow Aura")];
+        board.players[1].stable = vec![card("Basic Unicorn")];
+
+        let state = resolving(&board, 0, Effect::Bring(Selector { zone: Area::Hand, ..Selector::default() }));
+        assert!(state.react_metadata.as_ref().unwrap().follow_up == ResponseOp::Bring);
+        assert!(choices(&state) == vec![MoveType::Play(1), MoveType::Play(2)], "Only permanents can be brought.");
+        let err = validate_choice(&state, &Move::new(0, MoveType::Play(0)));
+        assert!(matches!(err, Err(LogicError::InvalidChoice { zone: Zone::Hand(0), index: 0 })));
+
+        // The brought card enters the stable, so its own ability goes on the stack.
+        let (action, next) = resolve(&state, &Move::new(0, MoveType::Play(1))).unwrap();
+        assert!(action.atype == ActionType::Place && next.board.players[0].stable.has_card("Seductive Unicorn"));
+        assert!(next.pending.last().unwrap().this == Some((Zone::Stable(0), 0)));
+        assert!(Effect::Bring(Selector::default()).check().is_err());
+    }
+
+    // Player one plays the first card of `hand` from a Rainbow Apocalypse game and player two lets it resolve.
+    fn rainbow_played(hand: &[&str], stables: [&[&str]; 2]) -> (Game, Vec<GameState>) {
+        let cards = |names: &[&str]| -> Cards { names.iter().map(|n| card(n)).collect() };
+        let mut board = Board::new_with_sets(&[Set::Base, Set::RainbowApocalypse], 2);
+        board.players[0].hand = cards(hand);
+        board.players[0].stable = cards(stables[0]);
+        board.players[1].stable = cards(stables[1]);
+        return branches_after(&board, &[Move::new(0, MoveType::Play(0)), Move::new(1, MoveType::Pass)]);
+    }
+
+    fn moves(game: &Game) -> Vec<MoveType> {
+        return legal_actions(game.current_state(), 0).into_iter().map(|m| m.mtype).collect();
+    }
+
+    #[test]
+    fn test_rainbow_unicorn() {
+        // Any Basic Unicorn in the hand may come along, Chromatic Unicorn included.
+        let (game, branches) = rainbow_played(&["Rainbow Unicorn", "Basic Unicorn", "Chromatic Unicorn", "Neigh"], [&[], &[]]);
+        assert!(moves(&game) == vec![MoveType::Play(0), MoveType::Play(1), MoveType::Pass]);
+        assert!(branches.len() == 3 && branches[1].board.players[0].stable.has_card("Chromatic Unicorn"));
+    }
+
+    #[test]
+    fn test_prismatic_unicorn() {
+        let (game, branches) = rainbow_played(&["Prismatic Unicorn"], [&[], &[]]);
+        let rainbows = game.current_state().board.deck.iter().filter(|c| c.is_rainbow()).count();
+        assert!(branches.len() == rainbows + 1, "Any Rainbow card of the deck, or none.");
+    }
+
+    #[test]
+    fn test_rainbow_burst() {
+        // Bringing is not optional, and the brought unicorn resolves its own ability.
+        let (mut game, _) = rainbow_played(&["Rainbow Burst", "Neigh", "Storm Chaser Unicorn"], [&[], &["Chromatic Unicorn"]]);
+        assert!(moves(&game) == vec![MoveType::Play(1)]);
+        game.apply(&Move::new(0, MoveType::Play(1))).unwrap();
+        assert!(moves(&game) == vec![MoveType::Target(1, 0), MoveType::Pass]);
+    }
+
+    #[test]
+    fn test_rainbow_shield() {
+        // Storm Chaser Unicorn cannot reach the shielded Rainbow cards, the shield included.
+        let stables: [&[&str]; 2] = [&["Pot of Gold"], &["Chromatic Unicorn", "Rainbow Shield", "Basic Unicorn"]];
+        let (game, _) = rainbow_played(&["Storm Chaser Unicorn"], stables);
+        assert!(moves(&game) == vec![MoveType::Target(0, 0), MoveType::Pass]);
+    }
+
+    #[test]
+    fn test_can_start() {
+        let mut board = Board::new_base_game(2);
+        let state = GameState::new(&board, &PhaseType::Play);
+        assert!(!Effect::Destroy(Selector::from(Filter::Unicorn)).can_start(&state, 0));
+        assert!(Effect::Draw(1).can_start(&state, 0));
+
+        board.players[1].stable = vec![card("Baby Unicorn")];
+        let state = GameState::new(&board, &PhaseType::Play);
+        let effect = Effect::Sequence(vec![Effect::Destroy(Selector::from(Filter::Unicorn)), Effect::Draw(1)]);
+        assert!(effect.can_start(&state, 0));
+        assert!(!Effect::Sacrifice(Selector::from(Filter::Unicorn)).can_start(&state, 0));
+    }
+
+    #[test]
+    fn test_this_card() {
+        let mut board = Board::new_base_game(2);
+        board.players[0].stable = vec![card("Basic Unicorn"), card("Unicorn Phoenix"), card("Basic Unicorn")];
+        board.discard = vec![card("Unicorn Poison")];
+        let others: Selector = serde_json::from_str(r#"{ "cards": "Unicorn", "exclude": ["This"] }"#).unwrap();
+        let effect = Effect::Sequence(vec![Effect::Destroy(others.clone()), Effect::Destroy(others)]);
+
+        let mut state = GameState::new(&board, &PhaseType::Play);
+        state.pending = vec![Pending::new(0, card("Unicorn Phoenix"), &effect).at(Zone::Stable(0), 1)];
+        let state = advance(state).unwrap();
+        assert!(choices(&state) == vec![MoveType::Target(0, 0), MoveType::Target(0, 2)]);
+        let err = validate_choice(&state, &Move::new(0, MoveType::Target(0, 1)));
+        assert!(matches!(err, Err(LogicError::InvalidTarget { player: 0, index: 1 })));
+
+        // Destroying the unicorn before it moves the phoenix down, which is still left out.
+        let state = advance(resolve(&state, &Move::new(0, MoveType::Target(0, 0))).unwrap().1).unwrap();
+        assert!(state.pending[0].this == Some((Zone::Stable(0), 0)));
+        assert!(choices(&state) == vec![MoveType::Target(0, 1)]);
+
+        let search = Effect::Search(Selector { zone: Area::Discard, ..Selector::default() });
+        let state = resolving(&board, 1, search);
+        assert!(choices(&state) == vec![MoveType::Search(0)]);
+        let (action, next) = resolve(&state, &Move::new(1, MoveType::Search(0))).unwrap();
+        assert!(action.card.name() == "Unicorn Poison" && next.board.discard.is_empty());
+    }
+
+    #[test]
+    fn test_replacements() {
+        let mut board = Board::new_base_game(2);
+        board.players[0].stable = vec![card("Basic Unicorn"), card("Black Knight Unicorn"), card("Stabby the Unicorn")];
+        board.players[1].stable = vec![card("Basic Unicorn")];
+        let poison = Effect::Destroy(Selector::from(Filter::Unicorn));
+
+        // The knight offers to go in place of any other unicorn of its stable.
+        let state = resolving(&board, 1, poison.clone());
+        let state = advance(resolve(&state, &Move::new(1, MoveType::Target(0, 0))).unwrap().1).unwrap();
+        assert!(state.phase == PhaseType::Effect && state.react_metadata.as_ref().unwrap().follow_up == ResponseOp::Replace);
+        assert!(choices(&state) == vec![MoveType::Target(0, 1)]);
+        let knighted = advance(resolve(&state, &Move::new(0, MoveType::Target(0, 1))).unwrap().1).unwrap();
+        assert!(knighted.pending.is_empty() && knighted.board.discard.has_card("Black Knight Unicorn"));
+        assert!(knighted.board.players[0].stable.has_card("Basic Unicorn"));
+
+        // Passing on the knight lets Stabby go, then its owner may destroy a unicorn in return.
+        let state = resolving(&board, 1, poison.clone());
+        let state = advance(resolve(&state, &Move::new(1, MoveType::Target(0, 2))).unwrap().1).unwrap();
+        let (action, state) = resolve(&state, &Move::new(0, MoveType::Pass)).unwrap();
+        assert!(action.atype == ActionType::Destroy && action.card.name() == "Stabby the Unicorn");
+        let state = advance(state).unwrap();
+        assert!(state.phase == PhaseType::Effect && state.react_metadata.as_ref().unwrap().response_user == vec![0]);
+        assert!(choices(&state).contains(&MoveType::Target(1, 0)));
+
+        // Mandatory replacements do not ask.
+        let stubborn = unregistered_card(r#"[{ "name": "Stubborn Unicorn", "ctype": "MagicUnicorn", "expansion": "Homebrew Replacements",
+                                              "replacement": { "guards": "This", "on": ["Destroy"], "instead": { "Draw": 1 }, "mandatory": true } }]"#,
+                                         "Stubborn Unicorn");
+        board.players[1].stable = vec![stubborn];
+        let state = resolving(&board, 0, poison);
+        let (_, state) = resolve(&state, &Move::new(0, MoveType::Target(1, 0))).unwrap();
+        let state = advance(state).unwrap();
+        assert!(state.pending.is_empty() && state.board.players[1].stable.has_card("Stubborn Unicorn"));
+        assert!(state.board.players[1].hand.len() == 1);
+    }
+
+    #[test]
+    fn test_steal_triggers() {
+        let steals = r#"[{ "name": "Homesick Unicorn", "ctype": "MagicUnicorn", "expansion": "Homebrew Steals", "leaves": { "Draw": 1 } },
+                         { "name": "Padlock", "ctype": "Upgrade", "expansion": "Homebrew Steals",
+                           "modifiers": [{ "modifier": { "CannotBeStolen": { "Type": "MagicUnicorn" } } }] }]"#;
+        let mut board = Board::new_base_game(2);
+        board.players[0].hand = vec![card("Neigh")];
+        board.players[1].stable = vec![unregistered_card(steals, "Homesick Unicorn"), card("Seductive Unicorn")];
+        let steal = Effect::Steal(Selector::from(Filter::Unicorn));
+
+        // The old owner's ability resolves first, then the new owner's.
+        let state = resolving(&board, 0, steal.clone());
+        let (action, state) = resolve(&state, &Move::new(0, MoveType::Target(1, 0))).unwrap();
+        assert!(action.atype == ActionType::Steal && state.board.players[0].stable.has_card("Homesick Unicorn"));
+        let state = advance(state).unwrap();
+        assert!(state.pending.is_empty() && state.board.players[1].hand.len() == 1);
+
+        let state = resolving(&board, 0, steal.clone());
+        let state = advance(resolve(&state, &Move::new(0, MoveType::Target(1, 1))).unwrap().1).unwrap();
+        assert!(state.phase == PhaseType::Effect && state.react_metadata.as_ref().unwrap().response_user == vec![0]);
+        assert!(choices(&state) == vec![MoveType::Discard(0)], "Seductive asks its new owner.");
+        assert!(state.pending.last().unwrap().this == Some((Zone::Stable(0), 0)));
+
+        // Protected cards are not offered.
+        board.players[1].stable.push(unregistered_card(steals, "Padlock"));
+        let state = resolving(&board, 0, steal);
+        assert!(state.pending.is_empty(), "Nothing left to steal.");
+        let state = resolving(&board, 0, Effect::Steal(Selector::default()));
+        assert!(choices(&state) == vec![MoveType::Target(1, 2)]);
+        let err = validate_choice(&state, &Move::new(0, MoveType::Target(1, 0)));
+        assert!(matches!(err, Err(LogicError::InvalidTarget { player: 1, index: 0 })));
+    }
+
+    #[test]
+    fn test_baby_unicorn_redirect() {
+        let mut board = Board::new_base_game(2);
+        board.nursery.clear();
+        board.players[0].stable = vec![card("Baby Unicorn"), card("Basic Unicorn")];
+
+        for effect in [Effect::Destroy(Selector::default()), Effect::Sacrifice(Selector::default()), Effect::ReturnToHand(Selector::default())] {
+            let state = resolving(&board, 0, effect);
+            let (action, state) = resolve(&state, &Move::new(0, MoveType::Target(0, 0))).unwrap();
+            assert!(action.atype == ActionType::NoOp);
+            let state = advance(state).unwrap();
+            assert!(state.pending.is_empty() && state.board.nursery.has_card("Baby Unicorn"));
+            assert!(state.board.players[0].stable.len() == 1 && state.board.players[0].hand.is_empty() && state.board.discard.is_empty());
+        }
+    }
+}
@@ -0,0 +1,184 @@
+// Agents running as separate processes, in any language. They speak the same JSON lines as
+// remote seats do over TCP: every decision arrives on stdin as a `your_move` message, whose
+// observation tells react prompts apart, and the bot answers on stdout with a `move` message.
+//
+// A bot that takes longer than its time limit, or answers with anything but a listed move,
+// gets a `timeout` or `error` message and its seat passes when it can. A bot that exits or
+// closes its output forfeits the seat for the rest of the game.
+
+// STD
+use std::io::{self, BufRead, BufReader};
+use std::path::Path;
+use std::process::{Child, ChildStdin, Command, Stdio};
+use std::sync::mpsc::{channel, Receiver, RecvTimeoutError};
+use std::thread;
+use std::time::Duration;
+
+// UU
+use crate::agent::Agent;
+use crate::moves::Move;
+use crate::observation::observe;
+use crate::server::{default_move, send, ClientMessage, ServerMessage};
+use crate::state::GameState;
+
+pub struct ExternalAgent {
+    name: String,
+    child: Child,
+    stdin: ChildStdin,
+    // Lines the bot wrote, read on a thread of their own so waiting can time out.
+    lines: Receiver<String>,
+    time_limit: Duration,
+    forfeited: bool,
+    // Decisions the bot missed or got wrong.
+    pub strikes: usize
+}
+
+impl ExternalAgent {
+    /// Starts `program` with `args`, allowing it `time_limit` per decision.
+    pub fn spawn(program: &str, args: &[String], time_limit: Duration) -> io::Result<Self> {
+        let mut child = Command::new(program).args(args)
+                                             .stdin(Stdio::piped())
+                                             .stdout(Stdio::piped())
+                                             .spawn()?;
+        let stdin = child.stdin.take().unwrap();
+        let stdout = child.stdout.take().unwrap();
+
+        let (outbox, lines) = channel();
+        thread::spawn(move || {
+            for line in BufReader::new(stdout).lines() {
+                let Ok(line) = line else {
+                    break;
+                };
+                if outbox.send(line).is_err() {
+                    break;
+                }
+            }
+        });
+
+        let name = Path::new(program).file_name().map_or(program.to_string(), |n| n.to_string_lossy().to_string());
+        return Ok(ExternalAgent { name, child, stdin, lines, time_limit, forfeited: false, strikes: 0 });
+    }
+
+    fn forfeit(&mut self) {
+        self.forfeited = true;
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
+
+    // Plays the default move after a mistake, letting the bot know why.
+    fn strike(&mut self, moves: &[Move], message: ServerMessage) -> usize {
+        self.strikes += 1;
+        if send(&mut self.stdin, &message).is_err() {
+            self.forfeit();
+        }
+        return default_index(moves);
+    }
+}
+
+fn default_index(moves: &[Move]) -> usize {
+    let mv = default_move(moves);
+    return moves.iter().position(|m| *m == mv).unwrap();
+}
+
+impl Agent for ExternalAgent {
+    fn name(&self) -> String {
+        return self.name.clone();
+    }
+
+    fn choose(&mut self, state: &GameState, player: usize, moves: &[Move]) -> usize {
+        if self.forfeited {
+            return default_index(moves);
+        }
+
+        // Anything still buffered answered an earlier prompt too late.
+        while self.lines.try_recv().is_ok() {}
+
+        let timeout_ms = Some(self.time_limit.as_millis() as u64);
+        let prompt = ServerMessage::YourMove { observation: observe(state, Some(player)), moves: moves.to_vec(), timeout_ms };
+        if send(&mut self.stdin, &prompt).is_err() {
+            self.forfeit();
+            return default_index(moves);
+        }
+
+        return match self.lines.recv_timeout(self.time_limit) {
+            Ok(line) => match serde_json::from_str(&line) {
+                Ok(ClientMessage::Move { mv }) => match moves.iter().position(|m| *m == mv) {
+                    Some(idx) => idx,
+                    None => self.strike(moves, ServerMessage::Error { message: "that move is not listed".to_string() })
+                },
+                Ok(_) => self.strike(moves, ServerMessage::Error { message: "expected a move".to_string() }),
+                Err(e) => self.strike(moves, ServerMessage::Error { message: format!("malformed message: {}", e) })
+            },
+            Err(RecvTimeoutError::Timeout) => {
+                let mv = default_move(moves);
+                self.strike(moves, ServerMessage::Timeout { mv })
+            },
+            Err(RecvTimeoutError::Disconnected) => {
+                self.forfeit();
+                default_index(moves)
+            }
+        };
+    }
+
+    fn forfeited(&self) -> bool {
+        return self.forfeited;
+    }
+}
+
+impl Drop for ExternalAgent {
+    fn drop(&mut self) {
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
+}
+
+#[cfg(test)]
+mod ExternalTest {
+    use super::*;
+    use crate::game::{seed_bytes, Game};
+    use crate::moves::{next_to_act, MoveType};
+    use crate::server::ServerTest::scenario;
+    use crate::state::Board;
+
+    // Answers every prompt with its first listed move, using nothing but sed.
+    const FIRST_MOVE_BOT: &str = r#"sed -u -n 's/.*"moves":\[\({"player":[0-9]*,"mtype":\("[A-Za-z]*"\|{[^}]*}\)}\).*/{"type":"move","mv":\1}/p'"#;
+
+    fn shell_bot(script: &str, time_limit: Duration) -> ExternalAgent {
+        return ExternalAgent::spawn("sh", &["-c".to_string(), script.to_string()], time_limit).unwrap();
+    }
+
+    #[test]
+    fn test_external_plays_a_game() {
+        let mut bot = shell_bot(FIRST_MOVE_BOT, Duration::from_secs(5));
+        assert!(bot.name() == "sh");
+
+        // The bot sits at both seats, going through every kind of prompt a game has.
+        let mut game = Game::new(&Board::new_base_game(2), true, Some(seed_bytes(8))).unwrap();
+        while let Some((player, moves)) = next_to_act(game.current_state()) {
+            let choice = bot.choose(game.current_state(), player, &moves);
+            assert!(choice == 0);
+            game.apply(&moves[choice]).unwrap();
+        }
+        assert!(bot.strikes == 0 && !bot.forfeited());
+    }
+
+    #[test]
+    fn test_timeouts_and_crashes() {
+        let game = scenario();
+        let state = game.current_state();
+        let moves = vec![Move::new(0, MoveType::Draw), Move::new(0, MoveType::Pass)];
+
+        let mut silent = shell_bot("while read line; do :; done", Duration::from_millis(50));
+        assert!(silent.choose(state, 0, &moves) == 1, "Timed out seats pass.");
+        assert!(silent.strikes == 1 && !silent.forfeited());
+
+        let mut confused = shell_bot(r#"while read line; do echo '{"type":"leave"}'; done"#, Duration::from_secs(5));
+        assert!(confused.choose(state, 0, &moves) == 1);
+        assert!(confused.strikes == 1);
+
+        let mut crashing = shell_bot("read line; exit 3", Duration::from_secs(5));
+        assert!(crashing.choose(state, 0, &moves) == 1);
+        assert!(crashing.forfeited());
+        assert!(crashing.choose(state, 0, &moves[..1]) == 0, "Forfeited seats keep getting default moves.");
+    }
+}
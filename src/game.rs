@@ -1,41 +1,62 @@
 use crate::state::*;
-use crate::cards::{BabyUnicorn, Card, Cards, QueryCards};
+use crate::cards::{Card, CardType};
+use crate::moves::{next_to_act, resolve_move, validate_move, Move};
 
-use std::collections::HashMap;
-use std::rc::Rc;
-use petgraph::visit::NodeIndexable;
 use rand::{seq::SliceRandom, SeedableRng};
 use rand_chacha::ChaChaRng;
 
-use petgraph::graph::{EdgeIndex, NodeIndex};
-use petgraph::visit::{EdgeRef, Bfs};
+use petgraph::graph::NodeIndex;
+use petgraph::visit::EdgeRef;
 use petgraph::{Graph, Incoming};
+use serde::{Deserialize, Serialize};
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct ActionEdge {
     pub card: Box<dyn Card>,
-    pub atype: ActionType
+    pub atype: ActionType,
+    // Move that produced this edge, if it was chosen by a player.
+    pub mv: Option<Move>
 }
 
 impl From<&Action> for ActionEdge {
     fn from(value: &Action) -> Self {
         return ActionEdge {
             card: value.card.clone(),
-            atype: value.atype.clone()
+            atype: value.atype.clone(),
+            mv: None
         }
     }
 }
 
 type GameGraph = Graph::<GameState, ActionEdge>;
 
-struct Game { graph: GameGraph }
+/// Spreads a short seed over the 32 bytes games are shuffled with.
+pub fn seed_bytes(seed: u64) -> [u8; 32] {
+    let mut bytes = [0; 32];
+    bytes[..8].copy_from_slice(&seed.to_le_bytes());
+    return bytes;
+}
+
+pub struct Game {
+    graph: GameGraph,
+    // Node of the game actually being played.
+    current: NodeIndex,
+    rng: ChaChaRng,
+    // Moves applied to reach the current node, oldest first.
+    moves: Vec<Move>
+}
+
 impl Game {
-    fn new(board: &Board, setup: bool, seed: Option<[u8; 32]>) -> Self {
+    pub fn new(board: &Board, setup: bool, seed: Option<[u8; 32]>) -> Result<Self, LogicError> {
+        return Game::new_with_rules(board, setup, seed, &Rules::for_players(board.players.len()));
+    }
+
+    pub fn new_with_rules(board: &Board, setup: bool, seed: Option<[u8; 32]>, rules: &Rules) -> Result<Self, LogicError> {
         let mut start_graph = GameGraph::new();
 
         let mut new_board = board.clone();
-        if let Some(s) = seed {
-            let mut rng = ChaChaRng::from_seed(s);
+        let mut rng = ChaChaRng::from_seed(seed.unwrap_or_default());
+        if seed.is_some() {
             new_board.deck.shuffle(&mut rng);
             new_board.nursery.shuffle(&mut rng);
         }
@@ -43,181 +64,217 @@ impl Game {
         // Do some extra setup to reduce tree depth that every game must do.
         // This part does not require any user input so can be done before tree is generated.
         if setup {
-            let mut new_nursery = new_board.nursery;
             for idx in 0..new_board.players.len() {
-                let (baby, new_nursery) = new_nursery.remove_one_card_with_type::<BabyUnicorn>().unwrap();
+                let baby = new_board.nursery.iter().position(|c| c.ctype() == CardType::BabyUnicorn)
+                                            .ok_or(LogicError::NurseryEmpty)?;
+                let baby = new_board.nursery.remove(baby);
                 new_board.players[idx].stable.push(baby);
             }
-            new_board.nursery = new_nursery;
 
             // discard two for discard pile
-            let discard_one = new_board.deck.pop().unwrap();
-            let discard_two = new_board.deck.pop().unwrap();
+            let discard_one = new_board.deck.pop().ok_or(LogicError::DeckEmpty)?;
+            let discard_two = new_board.deck.pop().ok_or(LogicError::DeckEmpty)?;
             new_board.discard.push(discard_one);
             new_board.discard.push(discard_two);
         }
 
-        start_graph.add_node(GameState::new(&new_board, &PhaseType::GameStart));
-        return Game {graph: start_graph};
+        let mut start = GameState::new(&new_board, &PhaseType::GameStart);
+        start.rules = rules.clone();
+        let root = start_graph.add_node(start);
+        return Ok(Game {graph: start_graph, current: root, rng, moves: vec![]});
     }
 
-    fn draw_phase(&mut self, player: usize, idx: &NodeIndex) -> Result<(), LogicError>{
-        let cur_state: &GameState = self.graph.node_weight(*idx).unwrap();
-        let mut board_copy = cur_state.board.clone();
-        let card = board_copy.deck.remove(0);
-
-        // Add current card to the machine
-        board_copy.players[player].hand.push(card.clone());
-        let new_node = GameState::new(&board_copy, &PhaseType::React);
-        let new_action = Action {
-            card: card,
-            atype: ActionType::Draw,
-            board: board_copy
-        };
-
-        let b_idx = self.graph.add_node(new_node);
-        self.graph.add_edge(*idx, b_idx, ActionEdge::from(&new_action));
-        return Ok(());
+    /// Continues a game from `state`, previously explored branches are not kept.
+    pub fn restore(state: &GameState, rng: ChaChaRng, moves: Vec<Move>) -> Self {
+        let mut graph = GameGraph::new();
+        let root = graph.add_node(state.clone());
+        return Game { graph, current: root, rng, moves };
     }
 
-    fn effect_phase(&mut self, player: usize, a_idx: &EdgeIndex) -> Result<(), LogicError> {
-        let node_idx = self.graph.edge_endpoints(*a_idx).unwrap().1;
-        let edge_action = self.graph.edge_weight_mut(*a_idx).unwrap().clone();
-        let game_state = self.graph.node_weight(node_idx).unwrap().clone();
-
-        let mut _generate_actions = |container: &Cards| -> Result<(), LogicError> {
-                for card in container {
-                    let action = Action {
-                        atype: edge_action.atype.clone(),
-                        card: edge_action.card.clone(),
-                        board: game_state.board.clone()
-                    };
-                    let reactions = card.effect(player, &game_state, &vec![action])?;
-
-                    if reactions.len() <= 0 {
-                        continue;
-                    }
-
-                    for reaction in reactions {
-                        let action = &reaction.effect_action;
-                        let mut phase_node = GameState::new(&action.board, &PhaseType::React);
-                        phase_node.react_metadata = Option::<ReactMetadata>::from(&reaction);
-                        let b_idx = self.graph.add_node(phase_node);
-                        self.graph.add_edge(node_idx, b_idx, ActionEdge::from(action));
-                    }
-                }
-
-                return Ok(());
-            };
-
-        // Any card can have an effect, so it is important that we keep track of the previous Node's phase
-        // and use that as history.
-        for hand_idx in 0..game_state.board.players.len() {
-            _generate_actions(&game_state.board.players[hand_idx].hand)?;
-            _generate_actions(&game_state.board.players[hand_idx].stable)?;
-        }
+    pub fn current_state(&self) -> &GameState {
+        return self.graph.node_weight(self.current).unwrap();
+    }
 
-        // We also need to add an no-op option.
-        let mut phase_node = GameState::new(&game_state.board, &PhaseType::React);
-        let no_idx = self.graph.add_node(phase_node);
-        self.graph.add_edge(node_idx, no_idx, ActionEdge { card: edge_action.card.clone(), atype: ActionType::NoOp });
+    /// Edge that led to the current node, if any move was made yet.
+    pub fn last_action(&self) -> Option<&ActionEdge> {
+        let edge = self.graph.edges_directed(self.current, Incoming).next()?;
+        return Some(edge.weight());
+    }
 
-        // We can just resolve the react phase here recursively. :O Just need to check history.
+    pub fn rng(&self) -> &ChaChaRng {
+        return &self.rng;
+    }
 
-        return Ok(());
+    pub fn moves(&self) -> &Vec<Move> {
+        return &self.moves;
     }
 
-    fn play_phase(&mut self, player: usize, idx: &NodeIndex) -> Result<(), LogicError> {
-        let board = self.graph.node_weight(*idx).unwrap().board.clone();
-        for (h_idx, card) in board.players[player].hand.iter().cloned().enumerate() {
-            if !card.phase_playable().contains(&PhaseType::Play) {
-                continue
-            }
+    /// Validates `mv` against the current node and advances to the resulting child,
+    /// reusing an already expanded child when one exists.
+    pub fn apply(&mut self, mv: &Move) -> Result<NodeIndex, LogicError> {
+        validate_move(self.current_state(), mv)?;
 
-            // Check if its possible to play said card.
-            // For this case, we don't care about history because its the first play of the stack.
-            let mut board_copy = board.clone();
-            board_copy.players[player].hand.remove(h_idx);
+        let child = self.child(self.current, mv)?;
+        self.current = child;
+        self.moves.push(mv.clone());
+        return Ok(child);
+    }
 
-            // Location can change, so we play the card to resolve the action.
-            let actions = card.play(player, &self.graph.node_weight(*idx).unwrap(), &vec![])?;
-            if actions.len() <= 0 {
-                continue;
-            }
+    /// Adds a branch below `node` for every move the next player to act could make there,
+    /// returning the children in the order of `legal_actions`.
+    // The game itself only follows `apply`, branches are for searching the tree.
+    #[allow(dead_code)]
+    pub fn expand(&mut self, node: NodeIndex) -> Result<Vec<NodeIndex>, LogicError> {
+        let state = self.graph.node_weight(node).ok_or(LogicError::UnknownNode)?;
+        let Some((_, moves)) = next_to_act(state) else {
+            return Ok(vec![]);
+        };
+        return moves.iter().map(|mv| self.child(node, mv)).collect();
+    }
 
-            for action in actions {
-                let mut phase_node = GameState::new(&action.effect_action.board, &PhaseType::React);
-                if let Some(_) =  action.follow_up {
-                    phase_node.react_metadata = Option::<ReactMetadata>::from(&action);
-                }
-                let b_idx = self.graph.add_node(phase_node);
-                self.graph.add_edge(*idx, b_idx, ActionEdge::from(&action.effect_action));
-            }
+    // Child of `node` reached with `mv`, added to the graph when not expanded yet.
+    fn child(&mut self, node: NodeIndex, mv: &Move) -> Result<NodeIndex, LogicError> {
+        let existing = self.graph.edges(node)
+                                 .find(|e| e.weight().mv.as_ref() == Some(mv))
+                                 .map(|e| e.target());
+        if let Some(child) = existing {
+            return Ok(child);
         }
 
-        return Ok(());
+        let state = self.graph.node_weight(node).ok_or(LogicError::UnknownNode)?;
+        let (action, next) = resolve_move(state, mv)?;
+        let mut edge = ActionEdge::from(&action);
+        edge.mv = Some(mv.clone());
+
+        let child = self.graph.add_node(next);
+        self.graph.add_edge(node, child, edge);
+        return Ok(child);
     }
 }
 
+/// Game continued from `board` in player one's Play phase after `moves`, with every state the next
+/// move could lead to, in the order of `legal_actions`.
+#[cfg(test)]
+pub fn branches_after(board: &Board, moves: &[Move]) -> (Game, Vec<GameState>) {
+    let start = GameState::new(board, &PhaseType::Play);
+    let mut game = Game::restore(&start, ChaChaRng::from_seed([0; 32]), vec![]);
+    for mv in moves {
+        game.apply(mv).unwrap();
+    }
+    let branches = game.expand(game.current).unwrap().iter().map(|node| game.graph[*node].clone()).collect();
+    return (game, branches);
+}
+
+#[cfg(test)]
 mod GameTest {
 
     use super::*;
     use crate::cards::*;
+    use crate::moves::{legal_actions, MoveType};
 
     #[test]
     fn test_effect_phase() {
         let mut board = Board::new_base_game(2);
 
         // We grab a unicorn phoenix and put it into the stable first.
-        let (phoenix_card, new_deck) = board.deck.remove_one_card_with_type::<UnicornPhoenix>().unwrap();
-        let (unicorn_poison, new_deck) = new_deck.remove_one_card_with_type::<UnicornPoison>().unwrap();
-        let (neigh, new_deck) = new_deck.remove_one_card_with_type::<Neigh>().unwrap();
+        let (phoenix_card, new_deck) = board.deck.remove_one_card_named("Unicorn Phoenix").unwrap();
+        let (unicorn_poison, new_deck) = new_deck.remove_one_card_named("Unicorn Poison").unwrap();
+        let (neigh, new_deck) = new_deck.remove_one_card_named("Neigh").unwrap();
 
         board.deck = new_deck;
         board.players[0].stable.push(phoenix_card);
         board.players[0].hand.push(neigh);
         board.players[1].hand.push(unicorn_poison);
 
-        // first we play the phoenix card
-        let mut game = Game::new(&board, true, None);
-
-        // Start with playing poison.
-        game.play_phase(1, &NodeIndex::new(0)).unwrap();
-        assert!(game.graph.node_count() == 4);
-
-        let mut save_edge = None;
-        let mut save_node = None;
-        let mut bfs = Bfs::new(&game.graph, NodeIndex::new(0));
-        let mut skip_first = true;
-        while let Some(nx) = bfs.next(&game.graph) {
-            if skip_first {
-                skip_first = false;
-                continue;
-            }
+        let mut start = GameState::new(&board, &PhaseType::Play);
+        start.turn = 1;
+        let mut game = Game::restore(&start, ChaChaRng::from_seed([0; 32]), vec![]);
+
+        // Start with playing poison, or drawing instead.
+        let children = game.expand(game.current).unwrap();
+        assert!(children.len() == 2);
+        game.apply(&Move::new(1, MoveType::Play(0))).unwrap();
+        game.apply(&Move::new(0, MoveType::Pass)).unwrap();
+
+        // The phoenix is the only unicorn to destroy.
+        let state = game.current_state();
+        assert!(state.phase == PhaseType::React);
+        assert!(state.react_metadata.as_ref().unwrap().follow_up == ResponseOp::Destroy);
+        let targets = game.expand(game.current).unwrap();
+        assert!(targets.len() == 1);
+
+        // Its owner may keep it by discarding a card instead, or let it go.
+        let revive = game.expand(targets[0]).unwrap();
+        assert!(revive.len() == 2);
+        let moves = legal_actions(game.graph.node_weight(targets[0]).unwrap(), 0);
+        assert!(moves == vec![Move::new(0, MoveType::Target(0, 0)), Move::new(0, MoveType::Pass)]);
+        assert!(game.graph.node_weight(revive[0]).unwrap().board.players[0].stable.has_card("Unicorn Phoenix"));
+        assert!(game.graph.node_weight(revive[1]).unwrap().board.discard.has_card("Unicorn Phoenix"));
+    }
 
-            let weight = game.graph.node_weight(nx).unwrap();
-            assert!(weight.phase == PhaseType::React);
-            let follow_up = &weight.react_metadata.as_ref().unwrap().follow_up;
-            // Find the card that we care about.
-            if follow_up == &ResponseOp::Destroy {
-                let mut edges = game.graph.neighbors_directed(nx, Incoming).detach();
-                while let Some(edge) = edges.next_edge(&game.graph) {
-                    save_node = Some(nx);
-                    save_edge = Some(edge);
-                }
-            }
-        }
+    #[test]
+    fn test_steal_branches() {
+        let mut board = Board::new_base_game(2);
+        let (seductive, new_deck) = board.deck.remove_one_card_named("Seductive Unicorn").unwrap();
+        let (neigh, new_deck) = new_deck.remove_one_card_named("Neigh").unwrap();
+        board.deck = new_deck;
+        board.players[0].hand = vec![seductive, neigh];
+        board.players[1].stable = vec![card_from_name("Basic Unicorn").unwrap(), card_from_name("Baby Unicorn").unwrap()];
+
+        let moves = [Move::new(0, MoveType::Play(0)), Move::new(1, MoveType::Pass), Move::new(0, MoveType::Discard(0))];
+        let (_, stolen) = branches_after(&board, &moves);
 
-        assert!(save_edge.is_some());
-        let save_edge = save_edge.unwrap();
-        let save_node = save_node.unwrap();
+        // One branch per unicorn player two could lose.
+        assert!(stolen.len() == 2);
+        for (state, name) in stolen.iter().zip(["Basic Unicorn", "Baby Unicorn"]) {
+            assert!(state.board.players[0].stable.has_card(name) && !state.board.players[1].stable.has_card(name));
+        }
+    }
 
-        // Try to destroy our unicorn card.
-        game.effect_phase(0, &save_edge).unwrap();
+    #[test]
+    fn test_dragon_branches() {
+        use crate::definitions::Set;
+        let mut board = Board::new_with_sets(&[Set::Base, Set::Dragons], 2);
+        let (slayer, new_deck) = board.deck.remove_one_card_named("Dragon Slayer Unicorn").unwrap();
+        board.deck = new_deck;
+        board.players[0].hand = vec![slayer];
+        board.players[1].stable = vec![card_from_name("Basic Unicorn").unwrap(), card_from_name("Red Dragon").unwrap(),
+                                       card_from_name("Gold Dragon").unwrap()];
+
+        let (game, branches) = branches_after(&board, &[Move::new(0, MoveType::Play(0)), Move::new(1, MoveType::Pass)]);
+
+        // Only the dragons can be destroyed, or the slayer lets them be.
+        let moves: Vec<_> = legal_actions(game.current_state(), 0).into_iter().map(|m| m.mtype).collect();
+        assert!(moves == vec![MoveType::Target(1, 1), MoveType::Target(1, 2), MoveType::Pass]);
+        assert!(branches.len() == 3);
+        let state = &branches[0];
+        assert!(!state.board.players[1].stable.has_card("Red Dragon") && state.board.discard.has_card("Red Dragon"));
+    }
 
-        // Verify the generated payload.
-        let edge_count: Vec<_> = game.graph.edges(save_node).collect();
-        assert!(edge_count.len() == 1);
+    #[test]
+    fn test_sacrifice_cost() {
+        let mut board = Board::new_base_game(2);
+        let (two_for_one, new_deck) = board.deck.remove_one_card_named("Two-For-One").unwrap();
+        board.deck = new_deck;
+        board.players[0].hand = vec![two_for_one];
+        let start = GameState::new(&board, &PhaseType::Play);
+        assert!(legal_actions(&start, 0) == vec![Move::new(0, MoveType::Draw)], "Nothing to sacrifice.");
+
+        board.players[0].stable = vec![card_from_name("Baby Unicorn").unwrap(), card_from_name("Basic Unicorn").unwrap()];
+        board.players[1].stable = vec![card_from_name("Basic Unicorn").unwrap()];
+        let nursery = board.nursery.len();
+        let (game, paid) = branches_after(&board, &[Move::new(0, MoveType::Play(0)), Move::new(1, MoveType::Pass)]);
+
+        // The cost comes first, one branch per card of player one's stable.
+        assert!(game.current_state().react_metadata.as_ref().unwrap().follow_up == ResponseOp::Sacrifice);
+        assert!(paid.len() == 2);
+
+        // The baby unicorn goes back to the nursery rather than the discard pile.
+        let state = &paid[0];
+        assert!(state.board.nursery.len() == nursery + 1 && !state.board.discard.has_card("Baby Unicorn"));
+        assert!(state.react_metadata.as_ref().unwrap().follow_up == ResponseOp::Destroy);
+        let state = &paid[1];
+        assert!(state.board.discard.has_card("Basic Unicorn"));
     }
 
     #[test]
@@ -226,30 +283,32 @@ mod GameTest {
         let mut board = Board::new_base_game(2);
 
         // Put a basic unicorn in hand to allow for playing in calculation.
-        let (card, new_deck) = board.deck.remove_one_card_with_type::<BasicUnicorn>().unwrap();
+        let (card, new_deck) = board.deck.remove_one_card_named("Basic Unicorn").unwrap();
         board.deck = new_deck;
         board.players[0].hand.push(card);
 
-        let mut game = Game::new(&board, true, None);
+        let mut game = Game::new(&board, true, None).unwrap();
+        game.apply(&Move::new(0, MoveType::Draw)).unwrap();
 
-        // We play which should have one neigh card to play.
-        game.play_phase(0, &NodeIndex::new(0)).unwrap();
+        // Both cards in hand can be played, or another drawn.
+        let children = game.expand(game.current).unwrap();
+        assert!(children.len() == 3);
 
-        // Two nodes, one start node, and one neigh play node.
-        assert!(game.graph.node_count() >= 2);
-        let gs: &GameState = game.graph.node_weight(NodeIndex::from(1)).unwrap();
+        // Playing the unicorn opens a neigh window, which one pass closes.
+        let passed = game.expand(children[0]).unwrap();
+        let gs: &GameState = game.graph.node_weight(*passed.last().unwrap()).unwrap();
         assert!(gs.board.discard.len() == 2);
-        assert!(gs.board.players[0].stable.count_card::<BasicUnicorn>() == 1);
-        assert!(gs.board.players[0].stable.count_card::<BabyUnicorn>() == 1);
+        assert!(gs.board.players[0].stable.count_card("Basic Unicorn") == 1);
+        assert!(gs.board.players[0].stable.count_card("Baby Unicorn") == 1);
     }
 
     #[test]
     fn test_draw_phase() {
         let board = Board::new_base_game(2);
-        let mut game = Game::new(&board, false, None);
+        let mut game = Game::new(&board, false, None).unwrap();
         let deck_count = board.deck.len();
 
-        game.draw_phase(0, &NodeIndex::new(0)).unwrap();
+        game.expand(game.current).unwrap();
         assert!(game.graph.node_count() >= 2);
 
         for out_going in &game.graph.raw_nodes()[1..] {
@@ -261,4 +320,47 @@ mod GameTest {
             assert!(edge.weight.atype == ActionType::Draw, "Should be draw.");
         }
     }
+
+    #[test]
+    fn test_apply() {
+        let mut board = Board::new_base_game(2);
+        let (card, new_deck) = board.deck.remove_one_card_named("Basic Unicorn").unwrap();
+        board.deck = new_deck;
+        board.players[0].hand.push(card);
+
+        let mut game = Game::new(&board, false, None).unwrap();
+        let root = game.current;
+
+        // Playing is not legal before drawing.
+        let err = game.apply(&Move::new(0, MoveType::Play(0)));
+        assert!(matches!(err, Err(LogicError::WrongPhase { player: 0, phase: PhaseType::GameStart })));
+        let err = game.apply(&Move::new(1, MoveType::Draw));
+        assert!(matches!(err, Err(LogicError::NotYourTurn { player: 1, turn: 0 })));
+        assert!(game.graph.node_count() == 1, "Illegal moves should not expand the graph.");
+
+        game.apply(&Move::new(0, MoveType::Draw)).unwrap();
+        game.apply(&Move::new(0, MoveType::Play(0))).unwrap();
+        game.apply(&Move::new(1, MoveType::Pass)).unwrap();
+
+        let state = game.current_state();
+        assert!(state.turn == 1 && state.phase == PhaseType::Turn);
+        assert!(state.board.players[0].stable.count_card("Basic Unicorn") == 1);
+
+        // Replaying a known move walks the existing edge.
+        game.current = root;
+        let node_count = game.graph.node_count();
+        game.apply(&Move::new(0, MoveType::Draw)).unwrap();
+        assert!(game.graph.node_count() == node_count);
+    }
+
+    #[test]
+    fn test_setup_errors() {
+        let mut board = Board::new_base_game(4);
+        board.nursery.truncate(3);
+        let err = Game::new(&board, true, None);
+        assert!(matches!(err, Err(LogicError::NurseryEmpty)), "Every player needs a baby unicorn.");
+
+        let game = Game::new(&Board::new_base_game(8), true, None).unwrap();
+        assert!(game.current_state().board.nursery.len() == 5);
+    }
 }
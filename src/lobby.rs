@@ -0,0 +1,638 @@
+// Many tables behind one listener. Connections start out in the lobby, where they create, list,
+// join and leave tables, seat bots or watch a table as spectators. A table starts as soon as every seat is taken and plays on
+// its own thread with its own seed; afterwards its players are back in the lobby.
+
+// STD
+use std::collections::{BTreeMap, HashMap};
+use std::io::{self, BufRead, BufReader};
+use std::net::{TcpListener, TcpStream};
+use std::sync::mpsc::{channel, Receiver, Sender};
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+// EXT
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaChaRng;
+use serde::{Deserialize, Serialize};
+
+// UU
+use crate::agent::{agent_by_name, Agent};
+use crate::definitions::Set;
+use crate::game::{seed_bytes, Game};
+use crate::server::{run_table, send, ClientMessage, Inbound, ServerConfig, ServerMessage, SnapshotPolicy, TableSeat};
+use crate::state::{Board, Rules};
+
+pub type SharedLobby = Arc<Mutex<Lobby>>;
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "status", rename_all = "snake_case")]
+pub enum TableStatus {
+    Waiting,
+    Playing,
+    Finished { winner: Option<usize> }
+}
+
+/// Public description of a table, as listed to lobby clients.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct TableInfo {
+    pub id: usize,
+    pub players: u8,
+    pub rules: Rules,
+    // Card sets the game is dealt from.
+    pub sets: Vec<Set>,
+    // Who sits at each seat, None for a free one.
+    pub seats: Vec<Option<String>>,
+    pub status: TableStatus
+}
+
+enum Occupant {
+    Remote(TcpStream),
+    Bot(Box<dyn Agent>)
+}
+
+struct Table {
+    info: TableInfo,
+    seed: u64,
+    // Filled while waiting, handed over to the game when it starts.
+    occupants: Vec<Option<Occupant>>,
+    // Where moves of seated connections go while the game runs.
+    inbox: Option<Sender<Inbound>>,
+    // Spectators waiting for the game to start.
+    spectators: Vec<(TcpStream, SnapshotPolicy)>
+}
+
+// A table dealt under the lobby lock, handed to the thread that plays it.
+struct DealtTable {
+    table: usize,
+    game: Game,
+    seats: Vec<TableSeat>,
+    inbox: Receiver<Inbound>,
+    config: ServerConfig
+}
+
+pub struct Lobby {
+    tables: BTreeMap<usize, Table>,
+    next_table: usize,
+    next_conn: usize,
+    // Picks seeds for tables created without one.
+    rng: ChaChaRng,
+    // Sets of tables created without any.
+    sets: Vec<Set>,
+    config: ServerConfig,
+    // Table and seat held by each connection.
+    seated: HashMap<usize, (usize, usize)>
+}
+
+impl Lobby {
+    pub fn new(seed: u64, sets: &[Set], config: ServerConfig) -> SharedLobby {
+        return Arc::new(Mutex::new(Lobby {
+            tables: BTreeMap::new(),
+            next_table: 0,
+            next_conn: 0,
+            rng: ChaChaRng::seed_from_u64(seed),
+            sets: sets.to_vec(),
+            config,
+            seated: HashMap::new()
+        }));
+    }
+
+    pub fn tables(&self) -> Vec<TableInfo> {
+        return self.tables.values().map(|t| t.info.clone()).collect();
+    }
+
+    pub fn create_table(&mut self, players: u8, seed: Option<u64>, rules: Option<Rules>, sets: Option<Vec<Set>>) -> Result<usize, String> {
+        if !(2..=8).contains(&players) {
+            return Err(format!("tables seat 2 to 8 players, not {}", players));
+        }
+        let sets = sets.unwrap_or_else(|| self.sets.clone());
+        if sets.is_empty() {
+            return Err("tables need at least one card set".to_string());
+        }
+        if let Some(set) = sets.iter().find(|s| s.cards().is_empty()) {
+            return Err(format!("no cards are known for the {} set", set));
+        }
+
+        let id = self.next_table;
+        self.next_table += 1;
+        let info = TableInfo {
+            id,
+            players,
+            rules: rules.unwrap_or(Rules::for_players(players as usize)),
+            sets,
+            seats: vec![None; players as usize],
+            status: TableStatus::Waiting
+        };
+        let seed = seed.unwrap_or_else(|| self.rng.gen());
+        let occupants = (0..players).map(|_| None).collect();
+        self.tables.insert(id, Table { info, seed, occupants, inbox: None, spectators: vec![] });
+        return Ok(id);
+    }
+
+    // Free seat of a waiting table, `seat` when asked for.
+    fn free_seat(&self, table: usize, seat: Option<usize>) -> Result<usize, String> {
+        let t = self.tables.get(&table).ok_or(format!("there is no table {}", table))?;
+        if t.info.status != TableStatus::Waiting {
+            return Err(format!("table {} already started", table));
+        }
+        return match seat {
+            Some(seat) if t.info.seats.get(seat) == Some(&None) => Ok(seat),
+            Some(seat) => Err(format!("seat {} of table {} is not free", seat, table)),
+            None => t.info.seats.iter().position(Option::is_none).ok_or(format!("table {} is full", table))
+        };
+    }
+
+    fn join(&mut self, conn: usize, stream: TcpStream, table: usize, seat: Option<usize>, name: Option<String>) -> Result<usize, String> {
+        if let Some((at, _)) = self.seated.get(&conn) {
+            return Err(format!("you already sit at table {}", at));
+        }
+        let seat = self.free_seat(table, seat)?;
+
+        let t = self.tables.get_mut(&table).unwrap();
+        t.info.seats[seat] = Some(name.unwrap_or(format!("player {}", conn)));
+        t.occupants[seat] = Some(Occupant::Remote(stream));
+        self.seated.insert(conn, (table, seat));
+        return Ok(seat);
+    }
+
+    fn add_bot(&mut self, table: usize, agent: &str) -> Result<usize, String> {
+        let seat = self.free_seat(table, None)?;
+        let t = self.tables.get_mut(&table).unwrap();
+        // Bots are seeded from their table so every table plays out independently.
+        let bot = agent_by_name(agent, t.seed.wrapping_add(seat as u64)).ok_or(format!("there is no agent {:?}", agent))?;
+        t.info.seats[seat] = Some(format!("bot {}", bot.name()));
+        t.occupants[seat] = Some(Occupant::Bot(bot));
+        return Ok(seat);
+    }
+
+    fn leave(&mut self, conn: usize) -> Result<usize, String> {
+        let (table, seat) = *self.seated.get(&conn).ok_or("you do not sit at any table")?;
+        let t = self.tables.get_mut(&table).unwrap();
+        if t.info.status != TableStatus::Waiting {
+            return Err(format!("table {} already started", table));
+        }
+        t.info.seats[seat] = None;
+        t.occupants[seat] = None;
+        self.seated.remove(&conn);
+        return Ok(table);
+    }
+
+    // Spectators may follow any table that has not finished yet.
+    fn check_watchable(&self, table: usize) -> Result<(), String> {
+        let t = self.tables.get(&table).ok_or(format!("there is no table {}", table))?;
+        if matches!(t.info.status, TableStatus::Finished { .. }) {
+            return Err(format!("table {} is over", table));
+        }
+        return Ok(());
+    }
+
+    fn watch(&mut self, table: usize, stream: TcpStream, snapshots: SnapshotPolicy) {
+        let t = self.tables.get_mut(&table).unwrap();
+        match &t.inbox {
+            Some(inbox) => {
+                let _ = inbox.send(Inbound::Watch(stream, snapshots));
+            },
+            None => t.spectators.push((stream, snapshots))
+        }
+    }
+
+    // Every seat taken and the game not yet started.
+    fn is_ready(&self, table: usize) -> bool {
+        return self.tables.get(&table).is_some_and(|t| {
+            return t.info.status == TableStatus::Waiting && t.info.seats.iter().all(Option::is_some);
+        });
+    }
+
+    // Deals the seats of a ready table into a game and marks it playing. Nothing is sent from here, as the lobby is
+    // locked. A table that cannot be dealt is closed.
+    fn deal(&mut self, table: usize) -> Result<DealtTable, (String, Vec<TcpStream>)> {
+        let t = self.tables.get_mut(&table).unwrap();
+        let board = Board::new_with_sets(&t.info.sets, t.info.players);
+        let game = match Game::new_with_rules(&board, true, Some(seed_bytes(t.seed)), &t.info.rules) {
+            Ok(game) => game,
+            Err(e) => return Err((format!("table {} could not start: {}", table, e), self.close(table)))
+        };
+
+        let seats = t.occupants.iter_mut().map(|occupant| match occupant.take().unwrap() {
+            Occupant::Remote(stream) => TableSeat::Remote(stream),
+            Occupant::Bot(agent) => TableSeat::Bot(agent)
+        }).collect();
+        let (outbox, inbox) = channel();
+        for (stream, snapshots) in t.spectators.drain(..) {
+            let _ = outbox.send(Inbound::Watch(stream, snapshots));
+        }
+        t.inbox = Some(outbox);
+        t.info.status = TableStatus::Playing;
+        return Ok(DealtTable { table, game, seats, inbox, config: self.config.clone() });
+    }
+
+    // Removes a table that will never be played, handing back the connections seated at or watching it.
+    fn close(&mut self, table: usize) -> Vec<TcpStream> {
+        let t = self.tables.remove(&table).unwrap();
+        self.seated.retain(|_, (at, _)| *at != table);
+        let mut streams: Vec<_> = t.occupants.into_iter().filter_map(|occupant| match occupant {
+            Some(Occupant::Remote(stream)) => Some(stream),
+            _ => None
+        }).collect();
+        streams.extend(t.spectators.into_iter().map(|(stream, _)| stream));
+        return streams;
+    }
+
+    fn finish(&mut self, table: usize, winner: Option<usize>) {
+        if let Some(t) = self.tables.get_mut(&table) {
+            t.info.status = TableStatus::Finished { winner };
+            t.inbox = None;
+        }
+        self.seated.retain(|_, (at, _)| *at != table);
+    }
+}
+
+// Plays a dealt table on a thread of its own, welcoming its players first.
+fn play_table(shared: &SharedLobby, dealt: DealtTable) {
+    let DealtTable { table, mut game, mut seats, inbox, config } = dealt;
+    let players = seats.len();
+    let shared = Arc::clone(shared);
+    thread::spawn(move || {
+        for (seat, table_seat) in seats.iter_mut().enumerate() {
+            if let TableSeat::Remote(stream) = table_seat {
+                let _ = send(stream, &ServerMessage::Welcome { seat, players });
+            }
+        }
+        let winner = run_table(&mut game, &mut seats, &inbox, &config);
+        shared.lock().unwrap().finish(table, winner);
+    });
+}
+
+fn reply(result: Result<ServerMessage, String>) -> ServerMessage {
+    return result.unwrap_or_else(|message| ServerMessage::Error { message });
+}
+
+// Answers one lobby request of `conn`, or forwards it to the game the connection is playing. Answers are worked out
+// under the lobby lock and written once it is released, so a slow client never holds up the others.
+fn handle_message(shared: &SharedLobby, conn: usize, stream: &TcpStream, message: ClientMessage) -> io::Result<()> {
+    let mut writer = stream.try_clone()?;
+    let mut watching = None;
+    // Whether this request took the last seat, which makes it the one to start the table.
+    let mut filled = false;
+    let response = {
+        let mut lobby = shared.lock().unwrap();
+        let playing = lobby.seated.get(&conn).cloned().and_then(|(table, seat)| {
+            return lobby.tables[&table].inbox.clone().map(|inbox| (inbox, seat));
+        });
+
+        match (message, playing) {
+            (ClientMessage::ListTables, _) => ServerMessage::Tables { tables: lobby.tables() },
+            (message, Some((inbox, seat))) => {
+                // The table answers for itself, also about lobby requests it will not take.
+                let _ = inbox.send(Inbound::Message(seat, message));
+                return Ok(());
+            },
+            (ClientMessage::Move { .. }, None) => ServerMessage::Error { message: "you are not playing a game".to_string() },
+            (ClientMessage::CreateTable { players, seed, rules, sets }, None) => {
+                reply(lobby.create_table(players, seed, rules, sets).map(|table| ServerMessage::TableCreated { table }))
+            },
+            (ClientMessage::Join { table, seat, name }, None) => {
+                let joined = lobby.join(conn, stream.try_clone()?, table, seat, name);
+                filled = joined.is_ok() && lobby.is_ready(table);
+                reply(joined.map(|seat| ServerMessage::Joined { table, seat }))
+            },
+            (ClientMessage::AddBot { table, agent }, None) => {
+                let seated = lobby.add_bot(table, &agent);
+                filled = seated.is_ok() && lobby.is_ready(table);
+                reply(seated.map(|seat| ServerMessage::BotSeated { table, seat }))
+            },
+            (ClientMessage::Watch { table, snapshots }, None) => {
+                watching = Some(snapshots);
+                reply(lobby.check_watchable(table).map(|_| ServerMessage::Watching { table }))
+            },
+            (ClientMessage::Leave, None) => reply(lobby.leave(conn).map(|table| ServerMessage::Left { table }))
+        }
+    };
+    send(&mut writer, &response)?;
+
+    match (response, watching) {
+        (ServerMessage::Watching { table }, Some(snapshots)) => {
+            // Handed over only once acknowledged, so the acknowledgement arrives ahead of anything the table sends.
+            let mut lobby = shared.lock().unwrap();
+            if let Err(message) = lobby.check_watchable(table) {
+                drop(lobby);
+                return send(&mut writer, &ServerMessage::Error { message });
+            }
+            lobby.watch(table, stream.try_clone()?, snapshots);
+        },
+        (ServerMessage::Joined { table, .. } | ServerMessage::BotSeated { table, .. }, _) if filled => {
+            // Should someone have left since, whoever fills the table again starts it.
+            let (dealt, seated_here) = {
+                let mut lobby = shared.lock().unwrap();
+                let seated_here = lobby.seated.get(&conn).is_some_and(|(at, _)| *at == table);
+                (lobby.is_ready(table).then(|| lobby.deal(table)), seated_here)
+            };
+            match dealt {
+                Some(Ok(dealt)) => play_table(shared, dealt),
+                Some(Err((message, streams))) => {
+                    for mut stream in streams {
+                        let _ = send(&mut stream, &ServerMessage::Error { message: message.clone() });
+                    }
+                    if !seated_here {
+                        send(&mut writer, &ServerMessage::Error { message })?;
+                    }
+                },
+                None => {}
+            }
+        },
+        _ => {}
+    }
+    return Ok(());
+}
+
+// A connection left: free its seat, or tell its game that the seat is gone.
+fn disconnect(shared: &SharedLobby, conn: usize) {
+    let mut lobby = shared.lock().unwrap();
+    let Some((table, seat)) = lobby.seated.get(&conn).cloned() else {
+        return;
+    };
+    match lobby.tables[&table].inbox.clone() {
+        Some(inbox) => {
+            let _ = inbox.send(Inbound::Closed(seat));
+        },
+        None => {
+            let _ = lobby.leave(conn);
+        }
+    }
+    lobby.seated.remove(&conn);
+}
+
+fn handle_connection(shared: SharedLobby, stream: TcpStream) -> io::Result<()> {
+    let conn = {
+        let mut lobby = shared.lock().unwrap();
+        lobby.next_conn += 1;
+        lobby.next_conn
+    };
+
+    let reader = BufReader::new(stream.try_clone()?);
+    for line in reader.lines() {
+        let Ok(line) = line else {
+            break;
+        };
+        if line.trim().is_empty() {
+            continue;
+        }
+        let handled = match serde_json::from_str(&line) {
+            Ok(message) => handle_message(&shared, conn, &stream, message),
+            Err(e) => send(&mut stream.try_clone()?, &ServerMessage::Error { message: format!("malformed message: {}", e) })
+        };
+        if handled.is_err() {
+            break;
+        }
+    }
+    disconnect(&shared, conn);
+    return Ok(());
+}
+
+/// Accepts lobby connections on `listener` for as long as it stays open.
+pub fn host(listener: &TcpListener, lobby: SharedLobby) -> io::Result<()> {
+    for stream in listener.incoming() {
+        let stream = stream?;
+        stream.set_nodelay(true)?;
+        let lobby = Arc::clone(&lobby);
+        thread::spawn(move || handle_connection(lobby, stream));
+    }
+    return Ok(());
+}
+
+#[cfg(test)]
+mod LobbyTest {
+    use super::*;
+    use crate::moves::next_to_act;
+    use std::io::Write;
+    use std::net::SocketAddr;
+    use std::time::Duration;
+
+    struct Client {
+        writer: TcpStream,
+        reader: BufReader<TcpStream>
+    }
+
+    impl Client {
+        fn connect(addr: SocketAddr) -> Self {
+            let stream = TcpStream::connect(addr).unwrap();
+            stream.set_nodelay(true).unwrap();
+            stream.set_read_timeout(Some(Duration::from_secs(10))).unwrap();
+            return Client { writer: stream.try_clone().unwrap(), reader: BufReader::new(stream) };
+        }
+
+        fn request(&mut self, message: ClientMessage) -> ServerMessage {
+            writeln!(self.writer, "{}", serde_json::to_string(&message).unwrap()).unwrap();
+            return self.recv();
+        }
+
+        fn recv(&mut self) -> ServerMessage {
+            let mut line = String::new();
+            self.reader.read_line(&mut line).unwrap();
+            return serde_json::from_str(&line).unwrap();
+        }
+
+        fn until_game_over(&mut self) -> Vec<ServerMessage> {
+            let mut received = vec![self.recv()];
+            while !matches!(received.last(), Some(ServerMessage::GameOver { .. })) {
+                received.push(self.recv());
+            }
+            return received;
+        }
+
+        // Plays the first legal move whenever asked, returning the public events seen until the game ends.
+        fn play_first_moves(&mut self) -> (Vec<String>, Option<usize>) {
+            let mut events = vec![];
+            loop {
+                match self.recv() {
+                    ServerMessage::YourMove { moves, .. } => {
+                        let answer = ClientMessage::Move { mv: moves[0].clone() };
+                        writeln!(self.writer, "{}", serde_json::to_string(&answer).unwrap()).unwrap();
+                    },
+                    ServerMessage::Event { text, .. } => events.push(text),
+                    ServerMessage::GameOver { winner, .. } => return (events, winner),
+                    _ => {}
+                }
+            }
+        }
+    }
+
+    fn start_lobby() -> (SocketAddr, SharedLobby) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let lobby = Lobby::new(9, &[Set::Base], ServerConfig::default());
+        let hosted = Arc::clone(&lobby);
+        thread::spawn(move || host(&listener, hosted));
+        return (addr, lobby);
+    }
+
+    // Events of the same game played locally, every seat taking its first legal move.
+    fn local_events(players: u8, seed: u64, sets: &[Set]) -> Vec<String> {
+        let mut game = Game::new(&Board::new_with_sets(sets, players), true, Some(seed_bytes(seed))).unwrap();
+        let mut events = vec![];
+        while let Some((_, moves)) = next_to_act(game.current_state()) {
+            game.apply(&moves[0]).unwrap();
+            events.push(game.last_action().unwrap().to_string());
+        }
+        return events;
+    }
+
+    #[test]
+    fn test_concurrent_tables() {
+        let (addr, lobby) = start_lobby();
+        let mut host_client = Client::connect(addr);
+        let create = |players, seed| ClientMessage::CreateTable { players, seed: Some(seed), rules: None, sets: None };
+        assert!(host_client.request(create(2, 21)) == ServerMessage::TableCreated { table: 0 });
+        assert!(host_client.request(create(3, 22)) == ServerMessage::TableCreated { table: 1 });
+
+        // Table 0 is one human against a bot, table 1 two humans and a bot.
+        let join = |table, name: &str| ClientMessage::Join { table, seat: None, name: Some(name.to_string()) };
+        let mut alice = Client::connect(addr);
+        assert!(alice.request(join(0, "alice")) == ServerMessage::Joined { table: 0, seat: 0 });
+        let mut bob = Client::connect(addr);
+        assert!(bob.request(join(1, "bob")) == ServerMessage::Joined { table: 1, seat: 0 });
+        let mut carol = Client::connect(addr);
+        assert!(carol.request(join(1, "carol")) == ServerMessage::Joined { table: 1, seat: 1 });
+
+        let bot = |table| ClientMessage::AddBot { table, agent: "first".to_string() };
+        assert!(host_client.request(bot(1)) == ServerMessage::BotSeated { table: 1, seat: 2 });
+        assert!(host_client.request(bot(0)) == ServerMessage::BotSeated { table: 0, seat: 1 });
+
+        let games: Vec<_> = vec![alice, bob, carol].into_iter().map(|mut client| thread::spawn(move || {
+            assert!(matches!(client.recv(), ServerMessage::Welcome { .. }));
+            return client.play_first_moves();
+        })).collect();
+        let results: Vec<_> = games.into_iter().map(|g| g.join().unwrap()).collect();
+
+        // Each table played exactly the game its seed deals, undisturbed by the other.
+        assert!(results[0].0 == local_events(2, 21, &[Set::Base]));
+        assert!(results[1].0 == local_events(3, 22, &[Set::Base]) && results[2].0 == results[1].0);
+
+        // Give the table threads a moment to report back.
+        for _ in 0..100 {
+            if lobby.lock().unwrap().tables().iter().all(|t| matches!(t.status, TableStatus::Finished { .. })) {
+                break;
+            }
+            thread::sleep(Duration::from_millis(10));
+        }
+        let ServerMessage::Tables { tables } = host_client.request(ClientMessage::ListTables) else {
+            panic!("Expected the table list.");
+        };
+        assert!(tables[0].status == TableStatus::Finished { winner: results[0].1 });
+        assert!(tables[1].seats == vec![Some("bob".to_string()), Some("carol".to_string()), Some("bot first".to_string())]);
+    }
+
+    #[test]
+    fn test_join_and_leave() {
+        let (addr, _) = start_lobby();
+        let mut client = Client::connect(addr);
+        let is_error = |message: ServerMessage| matches!(message, ServerMessage::Error { .. });
+
+        assert!(is_error(client.request(ClientMessage::Join { table: 3, seat: None, name: None })));
+        assert!(is_error(client.request(ClientMessage::CreateTable { players: 9, seed: None, rules: None, sets: None })));
+        assert!(client.request(ClientMessage::CreateTable { players: 2, seed: None, rules: None, sets: None }) == ServerMessage::TableCreated { table: 0 });
+        assert!(is_error(client.request(ClientMessage::AddBot { table: 0, agent: "genius".to_string() })));
+        assert!(is_error(client.request(ClientMessage::Leave)));
+
+        assert!(client.request(ClientMessage::Join { table: 0, seat: Some(1), name: None }) == ServerMessage::Joined { table: 0, seat: 1 });
+        assert!(is_error(client.request(ClientMessage::Join { table: 0, seat: None, name: None })));
+        assert!(is_error(client.request(ClientMessage::Move { mv: crate::moves::Move::new(1, crate::moves::MoveType::Draw) })));
+        assert!(client.request(ClientMessage::Leave) == ServerMessage::Left { table: 0 });
+
+        // A dropped connection gives its seat back.
+        let mut other = Client::connect(addr);
+        assert!(other.request(ClientMessage::Join { table: 0, seat: Some(0), name: None }) == ServerMessage::Joined { table: 0, seat: 0 });
+        drop(other);
+        thread::sleep(Duration::from_millis(50));
+        let ServerMessage::Tables { tables } = client.request(ClientMessage::ListTables) else {
+            panic!("Expected the table list.");
+        };
+        assert!(tables[0].seats == vec![None, None] && tables[0].status == TableStatus::Waiting);
+    }
+
+    #[test]
+    fn test_table_sets() {
+        let (addr, _) = start_lobby();
+        let mut host_client = Client::connect(addr);
+        let create = |seed, sets| ClientMessage::CreateTable { players: 2, seed: Some(seed), rules: None, sets: Some(sets) };
+        assert!(matches!(host_client.request(create(1, vec![])), ServerMessage::Error { .. }));
+        assert!(matches!(host_client.request(create(1, vec![Set::Nightmares])), ServerMessage::Error { .. }));
+        assert!(host_client.request(create(31, vec![Set::Base, Set::Dragons])) == ServerMessage::TableCreated { table: 0 });
+        // Dragons alone have no baby unicorns to deal.
+        assert!(host_client.request(create(32, vec![Set::Dragons])) == ServerMessage::TableCreated { table: 1 });
+
+        let ServerMessage::Tables { tables } = host_client.request(ClientMessage::ListTables) else {
+            panic!("Expected the table list.");
+        };
+        assert!(tables[0].sets == vec![Set::Base, Set::Dragons] && tables[1].sets == vec![Set::Dragons]);
+
+        // The expansion table plays the game its seed deals from both sets.
+        let mut alice = Client::connect(addr);
+        assert!(alice.request(ClientMessage::Join { table: 0, seat: None, name: None }) == ServerMessage::Joined { table: 0, seat: 0 });
+        assert!(host_client.request(ClientMessage::AddBot { table: 0, agent: "first".to_string() }) == ServerMessage::BotSeated { table: 0, seat: 1 });
+        assert!(matches!(alice.recv(), ServerMessage::Welcome { .. }));
+        assert!(alice.play_first_moves().0 == local_events(2, 31, &[Set::Base, Set::Dragons]));
+
+        // A table that cannot be dealt is closed, and everyone involved told so.
+        let mut bob = Client::connect(addr);
+        assert!(bob.request(ClientMessage::Join { table: 1, seat: None, name: None }) == ServerMessage::Joined { table: 1, seat: 0 });
+        assert!(host_client.request(ClientMessage::AddBot { table: 1, agent: "first".to_string() }) == ServerMessage::BotSeated { table: 1, seat: 1 });
+        assert!(matches!(host_client.recv(), ServerMessage::Error { .. }));
+        assert!(matches!(bob.recv(), ServerMessage::Error { .. }));
+        let ServerMessage::Tables { tables } = bob.request(ClientMessage::ListTables) else {
+            panic!("Expected the table list.");
+        };
+        assert!(tables.iter().all(|t| t.id != 1));
+        assert!(matches!(bob.request(ClientMessage::Leave), ServerMessage::Error { .. }));
+    }
+
+    #[test]
+    fn test_spectators() {
+        let (addr, _) = start_lobby();
+        let mut host_client = Client::connect(addr);
+        assert!(host_client.request(ClientMessage::CreateTable { players: 2, seed: Some(5), rules: None, sets: None })
+                == ServerMessage::TableCreated { table: 0 });
+        let watch = |table, snapshots| ClientMessage::Watch { table, snapshots };
+        assert!(matches!(host_client.request(watch(4, SnapshotPolicy::AtEnd)), ServerMessage::Error { .. }));
+
+        let mut delayed = Client::connect(addr);
+        assert!(delayed.request(watch(0, SnapshotPolicy::Delayed { moves: 2 })) == ServerMessage::Watching { table: 0 });
+        let mut at_end = Client::connect(addr);
+        assert!(at_end.request(watch(0, SnapshotPolicy::AtEnd)) == ServerMessage::Watching { table: 0 });
+        for _ in 0..2 {
+            host_client.request(ClientMessage::AddBot { table: 0, agent: "first".to_string() });
+        }
+
+        let expected = local_events(2, 5, &[Set::Base]);
+        for (client, delay) in [(&mut delayed, Some(2)), (&mut at_end, None)] {
+            let received = client.until_game_over();
+            assert!(matches!(&received[0], ServerMessage::Spectating { observation } if observation.players.iter().all(|p| p.hand.is_none())));
+
+            let mut events = vec![];
+            let mut snapshots = vec![];
+            for message in &received {
+                match message {
+                    ServerMessage::Event { text, observation } => {
+                        assert!(observation.players.iter().all(|p| p.hand.is_none()), "Live events stay redacted.");
+                        events.push(text.clone());
+                    },
+                    ServerMessage::Snapshot { after_moves, observation } => {
+                        // Never ahead of the delay, except the final state once every move is out.
+                        assert!(*after_moves + delay.unwrap_or(0) <= events.len() || *after_moves == expected.len());
+                        assert!(observation.players.iter().all(|p| p.hand.is_some()) && observation.deck.is_some());
+                        snapshots.push(*after_moves);
+                    },
+                    _ => {}
+                }
+            }
+            assert!(events == expected);
+
+            // Delayed snapshots trail the game, and everyone sees the final state.
+            let mut wanted: Vec<_> = match delay {
+                Some(delay) => (0..=expected.len() - delay).collect(),
+                None => vec![]
+            };
+            wanted.push(expected.len());
+            assert!(snapshots == wanted);
+        }
+    }
+}
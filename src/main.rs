@@ -1,8 +1,41 @@
+// Explicit returns and CamelCase test modules are house style.
+#![allow(clippy::needless_return, non_snake_case)]
+
+mod agent;
 mod cards;
+mod cli;
+mod definitions;
+mod effects;
+mod external;
 mod state;
 mod game;
+mod lobby;
+mod modifiers;
+mod moves;
+mod notation;
+mod observation;
+mod replay;
+mod save;
+mod serialize;
+mod server;
+mod targets;
+mod terminal;
+mod tui;
+
+use std::io;
+
+use clap::Parser;
 
+use crate::cli::{Cli, Command};
+use crate::save::CardSources;
 
 fn main() {
-    println!("Hello, world!");
+    let cli = Cli::parse();
+    let command = cli.command.unwrap_or(Command::Play { players: None, seed: None, tui: false, seats: vec![], load: None });
+    let sources = CardSources { sets: cli.sets, files: cli.cards };
+    let result = cli::load_cards(&sources.files).and_then(|_| cli::run(command, &sources, &mut io::stdin().lock(), &mut io::stdout()));
+    if let Err(e) = result {
+        eprintln!("{}", e);
+        std::process::exit(1);
+    }
 }
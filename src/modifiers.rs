@@ -0,0 +1,245 @@
+// Upgrades, Downgrades and some unicorns change the rules for as long as they sit in a stable. Their
+// definition lists the changes as modifiers, which apply to the stable's owner unless `who` says
+// otherwise, e.g. "you cannot play Instant cards" is
+//
+//   "modifiers": [{ "modifier": { "CannotPlay": { "Type": "Instant" } } }]
+//
+// Nothing is registered or cached, every rule that can be changed asks the stables in play through
+// the queries below, so modifiers take effect and lapse as cards enter and leave. Legendary unicorns
+// mostly change the rules for everyone at the table, with `"who": "All"`, so only one of them fits
+// in a stable at a time.
+
+// EXT
+use serde::{Deserialize, Serialize};
+
+// UU
+use crate::cards::{Card, CardType};
+use crate::effects::Who;
+use crate::state::{Board, Rules, Zone};
+use crate::targets::Filter;
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum Modifier {
+    // Cards that may be played each turn on top of the first.
+    ExtraPlays(usize),
+    // Matching cards cannot be played from the hand.
+    CannotPlay(Filter),
+    // Added to the hand limit, lowers it when negative.
+    HandLimit(isize),
+    // Matching cards in the stable cannot be destroyed.
+    CannotBeDestroyed(Filter),
+    // Matching cards in the stable cannot be stolen.
+    CannotBeStolen(Filter),
+    // Unicorns in the stable are Pandas, they do not count as unicorns for winning or for cards
+    // that affect Unicorn cards.
+    Pandas,
+    // Cards of this type in the stable count as unicorns.
+    CountsAsUnicorn(CardType),
+    // Added to the unicorns needed to win, lowers it when negative.
+    UnicornsToWin(isize),
+    // Cards drawn at the beginning of the turn on top of the first.
+    ExtraDraws(usize)
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Continuous {
+    // Players affected, relative to the owner of the stable.
+    #[serde(default)]
+    pub who: Who,
+    pub modifier: Modifier
+}
+
+/// Modifiers in play that apply to `player`.
+pub fn active(board: &Board, player: usize) -> Vec<&'static Modifier> {
+    let mut modifiers = vec![];
+    for (owner, p) in board.players.iter().enumerate() {
+        for card in p.stable.iter() {
+            let applies = card.definition().modifiers.iter().filter(|c| match c.who {
+                Who::You => player == owner,
+                Who::Others => player != owner,
+                Who::All => true
+            });
+            modifiers.extend(applies.map(|c| &c.modifier));
+        }
+    }
+    return modifiers;
+}
+
+/// Whether `card` counts as a unicorn where it is.
+pub fn is_unicorn(board: &Board, zone: &Zone, card: &dyn Card) -> bool {
+    let ctype = card.ctype();
+    let Zone::Stable(player) = zone else {
+        return ctype.is_unicorn();
+    };
+    let modifiers = active(board, *player);
+    if modifiers.contains(&&Modifier::Pandas) {
+        return false;
+    }
+    return ctype.is_unicorn() || modifiers.contains(&&Modifier::CountsAsUnicorn(ctype));
+}
+
+/// Unicorns counting towards a win in `player`'s stable.
+pub fn unicorns(board: &Board, player: usize) -> usize {
+    let zone = Zone::Stable(player);
+    return board.players[player].stable.iter().filter(|c| is_unicorn(board, &zone, c.as_ref())).count();
+}
+
+/// Unicorns `player` needs in their stable to win.
+pub fn unicorns_to_win(board: &Board, rules: &Rules, player: usize) -> usize {
+    let change: isize = active(board, player).iter().map(|m| match m {
+        Modifier::UnicornsToWin(change) => *change,
+        _ => 0
+    }).sum();
+    // Nobody wins with an empty stable.
+    return rules.unicorns_to_win.saturating_add_signed(change).max(1);
+}
+
+/// Cards `player` may play in one turn.
+pub fn plays_per_turn(board: &Board, player: usize) -> usize {
+    return 1 + active(board, player).iter().map(|m| match m {
+        Modifier::ExtraPlays(count) => *count,
+        _ => 0
+    }).sum::<usize>();
+}
+
+/// Cards `player` draws at the beginning of their turn.
+pub fn draws_per_turn(board: &Board, player: usize) -> usize {
+    return 1 + active(board, player).iter().map(|m| match m {
+        Modifier::ExtraDraws(count) => *count,
+        _ => 0
+    }).sum::<usize>();
+}
+
+/// Whether `card` may enter `player`'s stable by being played or brought there.
+pub fn can_enter(board: &Board, player: usize, card: &dyn Card) -> bool {
+    return !card.is_legendary() || !board.players[player].stable.iter().any(|c| c.is_legendary());
+}
+
+/// Whether `player` is allowed to play `card` from their hand at all.
+pub fn can_play(board: &Board, player: usize, card: &dyn Card) -> bool {
+    if card.is_permanent() && !can_enter(board, player, card) {
+        return false;
+    }
+    let zone = Zone::Hand(player);
+    return !active(board, player).iter().any(|m| match m {
+        Modifier::CannotPlay(filter) => filter.matches(board, &zone, card),
+        _ => false
+    });
+}
+
+/// Cards `player` may keep at the end of their turn.
+pub fn hand_limit(board: &Board, rules: &Rules, player: usize) -> usize {
+    let change: isize = active(board, player).iter().map(|m| match m {
+        Modifier::HandLimit(change) => *change,
+        _ => 0
+    }).sum();
+    return rules.hand_limit.saturating_add_signed(change);
+}
+
+/// Whether the card at `index` of `player`'s stable is safe from being destroyed.
+pub fn cannot_be_destroyed(board: &Board, player: usize, index: usize) -> bool {
+    return protected(board, player, index, |m| match m {
+        Modifier::CannotBeDestroyed(filter) => Some(filter),
+        _ => None
+    });
+}
+
+/// Whether the card at `index` of `player`'s stable is safe from being stolen.
+pub fn cannot_be_stolen(board: &Board, player: usize, index: usize) -> bool {
+    return protected(board, player, index, |m| match m {
+        Modifier::CannotBeStolen(filter) => Some(filter),
+        _ => None
+    });
+}
+
+fn protected(board: &Board, player: usize, index: usize, protection: fn(&Modifier) -> Option<&Filter>) -> bool {
+    let zone = Zone::Stable(player);
+    let Some(card) = board.players[player].stable.get(index) else {
+        return false;
+    };
+    return active(board, player).into_iter()
+                                .filter_map(protection)
+                                .any(|filter| filter.matches(board, &zone, card.as_ref()));
+}
+
+#[cfg(test)]
+mod ModifiersTest {
+    use super::*;
+    use crate::cards::{card_from_name, unregistered_card};
+
+    const DOWNGRADES: &str = r#"[{ "name": "Heavy Pockets", "ctype": "Downgrade", "expansion": "Homebrew Downgrades",
+                                   "modifiers": [{ "who": "Others", "modifier": { "HandLimit": -2 } },
+                                                 { "who": "All", "modifier": { "ExtraPlays": 1 } }] },
+                                 { "name": "Panda Costume", "ctype": "Downgrade", "expansion": "Homebrew Downgrades",
+                                   "modifiers": [{ "modifier": "Pandas" }] }]"#;
+
+    fn card(name: &str) -> Box<dyn Card> {
+        return card_from_name(name).unwrap_or_else(|| unregistered_card(DOWNGRADES, name));
+    }
+
+    #[test]
+    fn test_active() {
+        let mut board = Board::new_base_game(3);
+        board.players[1].stable = vec![card("Heavy Pockets"), card("Double Dutch")];
+
+        assert!(active(&board, 1) == vec![&Modifier::ExtraPlays(1), &Modifier::ExtraPlays(1)]);
+        assert!(plays_per_turn(&board, 1) == 3 && plays_per_turn(&board, 0) == 2);
+        let rules = Rules::for_players(3);
+        assert!(hand_limit(&board, &rules, 0) == 5 && hand_limit(&board, &rules, 1) == 7);
+    }
+
+    #[test]
+    fn test_pandas_and_protection() {
+        let mut board = Board::new_base_game(2);
+        board.players[0].stable = vec![card("Basic Unicorn"), card("Baby Unicorn"), card("Rainbow Aura")];
+        board.players[1].stable = vec![card("Basic Unicorn"), card("Panda Costume")];
+        board.players[1].hand = vec![card("Basic Unicorn")];
+
+        assert!(unicorns(&board, 0) == 2 && unicorns(&board, 1) == 0);
+        assert!(is_unicorn(&board, &Zone::Hand(1), board.players[1].hand[0].as_ref()));
+        assert!(cannot_be_destroyed(&board, 0, 1) && !cannot_be_destroyed(&board, 0, 2));
+        assert!(!cannot_be_destroyed(&board, 1, 0) && !cannot_be_stolen(&board, 0, 0));
+    }
+
+    #[test]
+    fn test_dragons() {
+        let mut board = Board::new_base_game(2);
+        board.players[0].stable = vec![card("Basic Unicorn"), card("Red Dragon"), card("Gold Dragon")];
+        let rules = Rules::for_players(2);
+        assert!(unicorns(&board, 0) == 1 && unicorns_to_win(&board, &rules, 0) == 6);
+        assert!(unicorns_to_win(&board, &rules, 1) == 7, "Only the owner needs fewer.");
+
+        board.players[0].stable.push(card("Dragon Bond"));
+        assert!(unicorns(&board, 0) == 3);
+        assert!(!is_unicorn(&board, &Zone::Hand(0), board.players[0].stable[1].as_ref()));
+        board.players[0].stable.push(card("Panda Costume"));
+        assert!(unicorns(&board, 0) == 0);
+    }
+
+    #[test]
+    fn test_legends() {
+        let mut board = Board::new_base_game(3);
+        board.players[2].stable = vec![card("Unicorn of Plenty"), card("Elder Unicorn")];
+        let rules = Rules::for_players(3);
+        assert!((0..3).all(|p| draws_per_turn(&board, p) == 2 && unicorns_to_win(&board, &rules, p) == 8));
+
+        let silent = card("Silent Sage Unicorn");
+        assert!(!can_play(&board, 2, silent.as_ref()) && can_play(&board, 0, silent.as_ref()), "One legend per stable.");
+        assert!(can_play(&board, 2, card("Basic Unicorn").as_ref()));
+        board.players[0].stable.push(silent);
+        assert!(!can_play(&board, 1, card("Neigh").as_ref()) && !can_play(&board, 2, card("Super Neigh").as_ref()));
+    }
+
+    #[test]
+    fn test_rainbows() {
+        let mut board = Board::new_base_game(2);
+        board.players[0].stable = vec![card("Pot of Gold")];
+        board.players[1].stable = vec![card("Grey Skies"), card("Chromatic Unicorn"), card("Rainbow Shield"), card("Basic Unicorn")];
+        assert!(hand_limit(&board, &Rules::for_players(2), 0) == 9);
+
+        let chromatic = card("Chromatic Unicorn");
+        assert!(!can_play(&board, 0, chromatic.as_ref()) && can_play(&board, 1, chromatic.as_ref()), "Grey Skies spares its owner.");
+        assert!(can_play(&board, 0, card("Basic Unicorn").as_ref()));
+        assert!(cannot_be_destroyed(&board, 1, 1) && cannot_be_stolen(&board, 1, 2) && !cannot_be_destroyed(&board, 1, 3));
+    }
+}
@@ -0,0 +1,658 @@
+// EXT
+use serde::{Deserialize, Serialize};
+
+// UU
+use crate::cards::{Card, Cards};
+use crate::effects::{self, choices, Pending};
+use crate::modifiers::{self, hand_limit};
+use crate::state::{Action, ActionType, Board, GameState, LogicError, PhaseType, ReactMetadata, ResponseOp, Zone};
+
+
+/// A single decision a player can make from a `GameState`.
+/// Indices refer to positions in the board at the time the move is made.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum MoveType {
+    Draw,
+    // Play the card at the given hand index.
+    Play(usize),
+    // Answer the current chain with the instant at the given hand index.
+    React(usize),
+    Pass,
+    // Discard the card at the given hand index.
+    Discard(usize),
+    // Choose the card at (player, stable index).
+    Target(usize, usize),
+    // Take the card at the given deck index.
+    Search(usize),
+    // Go ahead with an optional effect.
+    Accept,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct Move {
+    pub player: usize,
+    pub mtype: MoveType
+}
+
+impl Move {
+    pub fn new(player: usize, mtype: MoveType) -> Self {
+        return Move { player, mtype };
+    }
+}
+
+/// Players expected to answer the pending follow up.
+/// An empty response list means the player whose turn it is.
+pub fn responders(state: &GameState) -> Vec<usize> {
+    return match &state.react_metadata {
+        Some(meta) if meta.response_user.is_empty() => vec![state.turn],
+        Some(meta) => meta.response_user.clone(),
+        None => vec![]
+    };
+}
+
+/// Players who may make the next move from `state`.
+pub fn awaiting(state: &GameState) -> Vec<usize> {
+    return match state.phase {
+        PhaseType::React | PhaseType::Effect => responders(state),
+        _ => vec![state.turn]
+    };
+}
+
+/// First waiting player who has any move, with their moves. `None` once the game cannot continue.
+pub fn next_to_act(state: &GameState) -> Option<(usize, Vec<Move>)> {
+    return awaiting(state).into_iter()
+                          .map(|p| (p, legal_actions(state, p)))
+                          .find(|(_, moves)| !moves.is_empty());
+}
+
+/// Whether `player` could play `card` from their hand in the Play phase of `state`.
+pub fn playable(state: &GameState, player: usize, card: &dyn Card) -> bool {
+    if !card.phase_playable().contains(&PhaseType::Play) || !modifiers::can_play(&state.board, player, card) {
+        return false;
+    }
+    if card.definition().cost.as_ref().is_some_and(|c| !c.can_start(state, player)) {
+        return false;
+    }
+    if card.is_permanent() {
+        return true;
+    }
+    return card.definition().effect.as_ref().is_none_or(|e| e.can_start(state, player));
+}
+
+// Whether `player` could play the card at `h_idx` of their hand. Cards expect to already be out of the
+// hand when played, so the card is taken out of `probe` for the check and put back afterwards.
+fn playable_from_hand(probe: &mut GameState, player: usize, h_idx: usize) -> bool {
+    let card = probe.board.players[player].hand.remove(h_idx);
+    let playable = playable(probe, player, card.as_ref());
+    probe.board.players[player].hand.insert(h_idx, card);
+    return playable;
+}
+
+// Whether `player` could answer the chain of `state` with `card`.
+fn can_react(state: &GameState, player: usize, card: &dyn Card) -> bool {
+    if !card.phase_playable().contains(&PhaseType::React) || !modifiers::can_play(&state.board, player, card) {
+        return false;
+    }
+    return card.definition().counter.as_ref().is_some_and(|c| c.answers(&state.history));
+}
+
+/// Enumerates every move `player` may make from `state`.
+/// Only queries the cards, so it can be called on any node without touching the game graph.
+pub fn legal_actions(state: &GameState, player: usize) -> Vec<Move> {
+    let mut moves = vec![];
+    if player >= state.board.players.len() || state.winner().is_some() {
+        return moves;
+    }
+
+    let hand = &state.board.players[player].hand;
+    match state.phase {
+        PhaseType::GameStart | PhaseType::Turn | PhaseType::Draw => {
+            if player == state.turn && !state.board.deck.is_empty() {
+                moves.push(Move::new(player, MoveType::Draw));
+            }
+        },
+        PhaseType::Play => {
+            if player != state.turn {
+                return moves;
+            }
+
+            let mut probe = state.clone();
+            for h_idx in 0..hand.len() {
+                if playable_from_hand(&mut probe, player, h_idx) {
+                    moves.push(Move::new(player, MoveType::Play(h_idx)));
+                }
+            }
+
+            // Drawing is always an alternative to playing a card.
+            if !state.board.deck.is_empty() {
+                moves.push(Move::new(player, MoveType::Draw));
+            }
+        },
+        PhaseType::React | PhaseType::Effect => {
+            if !responders(state).contains(&player) {
+                return moves;
+            }
+
+            match &state.react_metadata.as_ref().unwrap().follow_up {
+                ResponseOp::Neigh => {
+                    for (h_idx, card) in hand.iter().enumerate() {
+                        if can_react(state, player, card.as_ref()) {
+                            moves.push(Move::new(player, MoveType::React(h_idx)));
+                        }
+                    }
+                    moves.push(Move::new(player, MoveType::Pass));
+                },
+                ResponseOp::Discard if state.pending.is_empty() => {
+                    // Discarding down to the hand limit.
+                    for h_idx in 0..hand.len() {
+                        moves.push(Move::new(player, MoveType::Discard(h_idx)));
+                    }
+                },
+                _ => {
+                    moves.extend(choices(state).into_iter().map(|mtype| Move::new(player, mtype)));
+                }
+            }
+
+            // Triggered effects are optional, and a follow up with no choices must not stall the chain.
+            let pass = Move::new(player, MoveType::Pass);
+            if (state.phase == PhaseType::Effect || moves.is_empty()) && !moves.contains(&pass) {
+                moves.push(pass);
+            }
+        }
+    }
+
+    return moves;
+}
+
+/// Explains why `mv` cannot be made from `state`, or returns `Ok` when it is legal.
+pub fn validate_move(state: &GameState, mv: &Move) -> Result<(), LogicError> {
+    let player = mv.player;
+    if player >= state.board.players.len() {
+        return Err(LogicError::UnknownPlayer(player));
+    }
+    if let Some(winner) = state.winner() {
+        return Err(LogicError::GameOver { winner });
+    }
+
+    let awaiting = awaiting(state);
+    if !awaiting.contains(&player) {
+        return Err(LogicError::NotYourTurn { player, turn: awaiting.first().cloned().unwrap_or(state.turn) });
+    }
+
+    let hand = &state.board.players[player].hand;
+    let in_hand = |idx: usize| -> Result<&Box<dyn Card>, LogicError> {
+        return hand.get(idx).ok_or(LogicError::CardNotInZone { zone: Zone::Hand(player), index: idx });
+    };
+    let wrong_phase = LogicError::WrongPhase { player, phase: state.phase.clone() };
+    let follow_up = state.react_metadata.as_ref().map(|m| m.follow_up.clone());
+
+    match (&state.phase, &mv.mtype, &follow_up) {
+        (PhaseType::React | PhaseType::Effect, _, None) => return Err(LogicError::NoPendingAction),
+        (PhaseType::React | PhaseType::Effect, _, _) => {},
+        (_, MoveType::Draw, _) => {
+            if state.board.deck.is_empty() {
+                return Err(LogicError::DeckEmpty);
+            }
+        },
+        (PhaseType::Play, MoveType::Play(idx), _) => {
+            let card = in_hand(*idx)?;
+            if !playable_from_hand(&mut state.clone(), player, *idx) {
+                return Err(LogicError::CardNotPlayable { player, card: card.name() });
+            }
+        },
+        _ => return Err(wrong_phase)
+    }
+
+    match (&mv.mtype, &follow_up) {
+        (_, None) => {},
+        (MoveType::React(idx), Some(ResponseOp::Neigh)) => {
+            let card = in_hand(*idx)?;
+            if !can_react(state, player, card.as_ref()) {
+                let top = state.history.last().ok_or(LogicError::NoPendingAction)?;
+                if card.phase_playable().contains(&PhaseType::React) && modifiers::can_play(&state.board, player, card.as_ref()) {
+                    return Err(LogicError::CannotBeNeighed { card: top.card.name() });
+                }
+                return Err(LogicError::CardNotPlayable { player, card: card.name() });
+            }
+        },
+        (MoveType::Target(..), Some(ResponseOp::Destroy | ResponseOp::Sacrifice | ResponseOp::Steal | ResponseOp::Return)) => {
+            effects::validate_choice(state, mv)?;
+        },
+        (MoveType::Discard(idx), Some(ResponseOp::Discard)) => {
+            in_hand(*idx)?;
+        },
+        (MoveType::Search(_), Some(ResponseOp::Search)) | (MoveType::Play(_), Some(ResponseOp::Bring)) => {
+            effects::validate_choice(state, mv)?;
+        },
+        (MoveType::Accept, Some(ResponseOp::Confirm)) => {},
+        (MoveType::Target(..), Some(ResponseOp::Replace)) => {},
+        (MoveType::Pass, Some(ResponseOp::Discard)) if state.phase == PhaseType::React && hand.len() > hand_limit(&state.board, &state.rules, player) => {
+            let limit = hand_limit(&state.board, &state.rules, player);
+            return Err(LogicError::HandLimitExceeded { player, hand_size: hand.len(), limit });
+        },
+        (MoveType::Pass, _) => {},
+        _ => return Err(wrong_phase)
+    }
+
+    // Anything left over is an edge case the checks above do not describe.
+    if !legal_actions(state, player).contains(mv) {
+        return Err(LogicError::IllegalMove(mv.clone()));
+    }
+    return Ok(());
+}
+
+/// Resolves a legal `mv` against `state`, returning the action taken and the resulting state.
+/// The caller is responsible for checking legality, see `legal_actions`.
+pub fn resolve_move(state: &GameState, mv: &Move) -> Result<(Action, GameState), LogicError> {
+    let player = mv.player;
+    let mut board = state.board.clone();
+
+    if !state.pending.is_empty() && !is_neigh_window(state) {
+        let (action, next) = effects::resolve(state, mv)?;
+        return Ok((action, continue_effects(next)?));
+    }
+
+    match (&state.phase, &mv.mtype) {
+        (_, MoveType::Draw) => {
+            let mut action = board.draw()?.ok_or(LogicError::DeckEmpty)?;
+            action.board.players[player].hand.push(action.card.clone());
+            if state.phase == PhaseType::Play {
+                // Drawing takes the place of playing a card.
+                let next = end_action(state, &action.board);
+                return Ok((action, next));
+            }
+
+            // Modifiers can add to the draw at the beginning of the turn.
+            for _ in 1..modifiers::draws_per_turn(&state.board, player) {
+                let Some(card) = action.board.deck.pop() else {
+                    break;
+                };
+                action.board.players[player].hand.push(card);
+            }
+            let next = state.child(&action.board, &PhaseType::Play);
+            return Ok((action, next));
+        },
+        (_, MoveType::Play(h_idx)) => {
+            let card = take_card(&mut board.players[player].hand, Zone::Hand(player), *h_idx)?;
+            let atype = if card.is_permanent() { ActionType::Place } else { ActionType::Discard };
+            let action = Action { card, atype, board: board.clone() };
+
+            // The card stays out of play until everyone had the chance to neigh it.
+            let mut next = state.child(&board, &PhaseType::React);
+            next.history = vec![action.clone()];
+            next.react_metadata = Some(ReactMetadata {
+                follow_up: ResponseOp::Neigh,
+                response_user: others(&board, player)
+            });
+            return Ok((action, next));
+        },
+        (_, MoveType::React(h_idx)) => {
+            let card = take_card(&mut board.players[player].hand, Zone::Hand(player), *h_idx)?;
+            board.discard.push(card.clone());
+            let action = Action { card, atype: ActionType::Instant, board: board.clone() };
+
+            // A reaction can be answered by anyone but the player who made it.
+            let mut next = state.child(&board, &PhaseType::React);
+            next.history = state.history.clone();
+            next.history.push(action.clone());
+            next.react_metadata = Some(ReactMetadata {
+                follow_up: ResponseOp::Neigh,
+                response_user: others(&board, player)
+            });
+            return Ok((action, next));
+        },
+        (PhaseType::React, MoveType::Pass) if is_neigh_window(state) => {
+            let action = pass_action(state)?;
+            let mut next = state.clone();
+            next.react_metadata.as_mut().unwrap().response_user.retain(|p| *p != player);
+            if next.react_metadata.as_ref().unwrap().response_user.is_empty() {
+                next = resolve_chain(state)?;
+            }
+            return Ok((action, next));
+        },
+        (_, MoveType::Pass) => {
+            let action = pass_action(state)?;
+            return Ok((action, end_turn(state, &board)));
+        },
+        (_, MoveType::Discard(h_idx)) => {
+            let card = take_card(&mut board.players[player].hand, Zone::Hand(player), *h_idx)?;
+            board.discard.push(card.clone());
+
+            let action = Action { card, atype: ActionType::Discard, board: board.clone() };
+            return Ok((action, end_turn(state, &board)));
+        },
+        _ => return Err(LogicError::NoPendingAction)
+    }
+}
+
+fn take_card(cards: &mut Cards, zone: Zone, index: usize) -> Result<Box<dyn Card>, LogicError> {
+    if index >= cards.len() {
+        return Err(LogicError::CardNotInZone { zone, index });
+    }
+    return Ok(cards.remove(index));
+}
+
+fn is_neigh_window(state: &GameState) -> bool {
+    return state.react_metadata.as_ref().is_some_and(|m| m.follow_up == ResponseOp::Neigh);
+}
+
+// Every player except `player`, in turn order starting after them.
+fn others(board: &Board, player: usize) -> Vec<usize> {
+    let count = board.players.len();
+    return (1..count).map(|offset| (player + offset) % count).collect();
+}
+
+fn pass_action(state: &GameState) -> Result<Action, LogicError> {
+    let last = state.history.last().ok_or(LogicError::NoPendingAction)?;
+    return Ok(Action { card: last.card.clone(), atype: ActionType::NoOp, board: state.board.clone() });
+}
+
+// Resolves the neigh chain once every player has passed on it.
+fn resolve_chain(state: &GameState) -> Result<GameState, LogicError> {
+    let played = state.history.first().ok_or(LogicError::NoPendingAction)?;
+    let mut board = state.board.clone();
+
+    // Each instant cancels the one below it, so an odd number cancels the played card.
+    if (state.history.len() - 1) % 2 == 1 {
+        board.discard.push(played.card.clone());
+        return Ok(end_action(state, &board));
+    }
+
+    let card = played.card.clone();
+    let zone = if card.is_permanent() { Zone::Stable(state.turn) } else { Zone::Discard };
+    board.cards_mut(&zone).push(card.clone());
+    let index = board.cards(&zone).len() - 1;
+
+    // The played card stays on the chain while its effect resolves.
+    let mut next = state.child(&board, &PhaseType::React);
+    next.history = vec![played.clone()];
+    if let Some(effect) = &card.definition().effect {
+        next.pending.push(Pending::new(state.turn, card.clone(), effect).at(zone.clone(), index));
+    }
+    // The cost is paid first.
+    if let Some(cost) = &card.definition().cost {
+        next.pending.push(Pending::new(state.turn, card.clone(), cost).at(zone, index));
+    }
+    return continue_effects(next);
+}
+
+// Resolves pending effects up to the next choice, ending the turn once there are none.
+fn continue_effects(state: GameState) -> Result<GameState, LogicError> {
+    let next = effects::advance(state)?;
+    if next.pending.is_empty() {
+        return Ok(end_action(&next, &next.board));
+    }
+    return Ok(next);
+}
+
+// Counts a finished play, the turn ends once the turn player has none left.
+fn end_action(state: &GameState, board: &Board) -> GameState {
+    let plays = state.plays + 1;
+    if plays < modifiers::plays_per_turn(board, state.turn) {
+        let mut next = state.child(board, &PhaseType::Play);
+        next.plays = plays;
+        return next;
+    }
+    return end_turn(state, board);
+}
+
+// Hands the turn to the next player once the current player is within the hand limit.
+fn end_turn(state: &GameState, board: &Board) -> GameState {
+    if board.players[state.turn].hand.len() > hand_limit(board, &state.rules, state.turn) {
+        let mut next = state.child(board, &PhaseType::React);
+        next.react_metadata = Some(ReactMetadata { follow_up: ResponseOp::Discard, response_user: vec![state.turn] });
+        return next;
+    }
+
+    let mut next = state.child(board, &PhaseType::Turn);
+    next.turn = (state.turn + 1) % board.players.len();
+    next.plays = 0;
+    return next;
+}
+
+#[cfg(test)]
+mod MovesTest {
+    use super::*;
+    use crate::cards::*;
+    use crate::state::*;
+
+    fn take(board: &mut Board, name: &str) -> Box<dyn Card> {
+        let (card, new_deck) = board.deck.remove_one_card_named(name).unwrap();
+        board.deck = new_deck;
+        return card;
+    }
+
+    #[test]
+    fn test_draw_phase_moves() {
+        let board = Board::new_base_game(2);
+        let state = GameState::new(&board, &PhaseType::Draw);
+
+        assert!(legal_actions(&state, 0) == vec![Move::new(0, MoveType::Draw)]);
+        assert!(legal_actions(&state, 1).is_empty(), "Not player two's turn.");
+    }
+
+    #[test]
+    fn test_play_phase_moves() {
+        let mut board = Board::new_base_game(2);
+        let unicorn = take(&mut board, "Basic Unicorn");
+        let neigh = take(&mut board, "Neigh");
+        let poison = take(&mut board, "Unicorn Poison");
+        board.players[0].hand = vec![unicorn, neigh, poison];
+
+        let state = GameState::new(&board, &PhaseType::Play);
+        let moves = legal_actions(&state, 0);
+
+        // Neigh is an instant and poison has no unicorn to destroy.
+        assert!(moves == vec![Move::new(0, MoveType::Play(0)), Move::new(0, MoveType::Draw)]);
+    }
+
+    #[test]
+    fn test_react_moves() {
+        let mut board = Board::new_base_game(2);
+        let neigh = take(&mut board, "Neigh");
+        let super_neigh = take(&mut board, "Super Neigh");
+        let unicorn = take(&mut board, "Basic Unicorn");
+        board.players[1].hand = vec![neigh.clone(), super_neigh.clone()];
+
+        let mut state = GameState::new(&board, &PhaseType::React);
+        state.react_metadata = Some(ReactMetadata { follow_up: ResponseOp::Neigh, response_user: vec![1] });
+        state.history = vec![Action { card: unicorn, atype: ActionType::Place, board: board.clone() }];
+
+        let moves = legal_actions(&state, 1);
+        assert!(moves.len() == 3);
+        assert!(moves.contains(&Move::new(1, MoveType::React(0))));
+        assert!(moves.contains(&Move::new(1, MoveType::React(1))));
+        assert!(moves.contains(&Move::new(1, MoveType::Pass)));
+        assert!(legal_actions(&state, 0).is_empty(), "Player one is not responding.");
+
+        // Nothing can answer a super neigh.
+        state.history.push(Action { card: super_neigh, atype: ActionType::Instant, board: board.clone() });
+        assert!(legal_actions(&state, 1) == vec![Move::new(1, MoveType::Pass)]);
+    }
+
+    #[test]
+    fn test_destroy_targets() {
+        let mut board = Board::new_base_game(2);
+        let unicorn = take(&mut board, "Basic Unicorn");
+        let neigh = take(&mut board, "Neigh");
+        let poison = take(&mut board, "Unicorn Poison");
+        board.players[1].stable = vec![neigh, unicorn];
+
+        let mut state = GameState::new(&board, &PhaseType::Play);
+        state.pending = vec![Pending::new(0, poison.clone(), poison.definition().effect.as_ref().unwrap())];
+        let state = effects::advance(state).unwrap();
+        assert!(state.react_metadata.as_ref().unwrap().follow_up == ResponseOp::Destroy);
+
+        // Only the unicorn is a valid target and the turn player chooses.
+        assert!(legal_actions(&state, 0) == vec![Move::new(0, MoveType::Target(1, 1))]);
+        assert!(legal_actions(&state, 1).is_empty());
+    }
+
+    #[test]
+    fn test_resolve_neighed_play() {
+        let mut board = Board::new_base_game(2);
+        let unicorn = take(&mut board, "Basic Unicorn");
+        let neigh = take(&mut board, "Neigh");
+        board.players[0].hand = vec![unicorn];
+        board.players[1].hand = vec![neigh];
+
+        let mut state = GameState::new(&board, &PhaseType::Play);
+        let (_, next) = resolve_move(&state, &Move::new(0, MoveType::Play(0))).unwrap();
+        assert!(next.phase == PhaseType::React);
+        assert!(next.board.players[0].hand.is_empty());
+        assert!(legal_actions(&next, 1).contains(&Move::new(1, MoveType::React(0))));
+
+        state = next;
+        let (_, next) = resolve_move(&state, &Move::new(1, MoveType::React(0))).unwrap();
+        assert!(legal_actions(&next, 0) == vec![Move::new(0, MoveType::Pass)]);
+
+        state = next;
+        let (_, next) = resolve_move(&state, &Move::new(0, MoveType::Pass)).unwrap();
+        assert!(next.phase == PhaseType::Turn && next.turn == 1);
+        assert!(next.board.players[0].stable.is_empty());
+        assert!(next.board.discard.has_card("Basic Unicorn"));
+        assert!(next.board.discard.has_card("Neigh"));
+    }
+
+    #[test]
+    fn test_resolve_destroy_phoenix() {
+        let mut board = Board::new_base_game(2);
+        let phoenix = take(&mut board, "Unicorn Phoenix");
+        let poison = take(&mut board, "Unicorn Poison");
+        let neigh = take(&mut board, "Neigh");
+        board.players[0].stable = vec![phoenix];
+        board.players[0].hand = vec![neigh];
+        board.players[1].hand = vec![poison];
+
+        let mut state = GameState::new(&board, &PhaseType::Play);
+        state.turn = 1;
+        let moves = [
+            Move::new(1, MoveType::Play(0)),
+            Move::new(0, MoveType::Pass),
+            Move::new(1, MoveType::Target(0, 0)),
+        ];
+        for mv in moves.iter() {
+            assert!(legal_actions(&state, mv.player).contains(mv), "{:?} should be legal.", mv);
+            state = resolve_move(&state, mv).unwrap().1;
+        }
+
+        // Phoenix may stay by discarding a card instead.
+        assert!(state.phase == PhaseType::Effect);
+        assert!(legal_actions(&state, 0) == vec![Move::new(0, MoveType::Target(0, 0)), Move::new(0, MoveType::Pass)]);
+        let (_, gone) = resolve_move(&state, &Move::new(0, MoveType::Pass)).unwrap();
+        assert!(gone.board.discard.has_card("Unicorn Phoenix") && gone.turn == 0);
+
+        let (action, state) = resolve_move(&state, &Move::new(0, MoveType::Target(0, 0))).unwrap();
+        assert!(action.atype == ActionType::NoOp && state.phase == PhaseType::React);
+        assert!(legal_actions(&state, 0) == vec![Move::new(0, MoveType::Discard(0))]);
+        let (_, next) = resolve_move(&state, &Move::new(0, MoveType::Discard(0))).unwrap();
+        assert!(next.board.players[0].stable.has_card("Unicorn Phoenix"));
+        assert!(next.board.discard.has_card("Neigh"));
+        assert!(next.turn == 0);
+    }
+
+    #[test]
+    fn test_validate_move() {
+        let mut board = Board::new_base_game(2);
+        let neigh = take(&mut board, "Neigh");
+        let super_neigh = take(&mut board, "Super Neigh");
+        let poison = take(&mut board, "Unicorn Poison");
+        board.players[0].stable = vec![neigh.clone()];
+        board.players[1].hand = vec![neigh];
+
+        let mut state = GameState::new(&board, &PhaseType::React);
+        state.react_metadata = Some(ReactMetadata { follow_up: ResponseOp::Neigh, response_user: vec![1] });
+        state.history = vec![Action { card: super_neigh, atype: ActionType::Instant, board: board.clone() }];
+
+        let err = validate_move(&state, &Move::new(1, MoveType::React(0)));
+        assert!(matches!(err, Err(LogicError::CannotBeNeighed { card: "Super Neigh" })));
+        let err = validate_move(&state, &Move::new(1, MoveType::React(3)));
+        assert!(matches!(err, Err(LogicError::CardNotInZone { zone: Zone::Hand(1), index: 3 })));
+        let err = validate_move(&state, &Move::new(0, MoveType::Pass));
+        assert!(matches!(err, Err(LogicError::NotYourTurn { player: 0, turn: 1 })));
+        assert!(validate_move(&state, &Move::new(1, MoveType::Pass)).is_ok());
+
+        state.pending = vec![Pending::new(0, poison.clone(), poison.definition().effect.as_ref().unwrap())];
+        state.history = vec![Action { card: poison, atype: ActionType::Discard, board: board.clone() }];
+        state.react_metadata = Some(ReactMetadata { follow_up: ResponseOp::Destroy, response_user: vec![] });
+        let err = validate_move(&state, &Move::new(0, MoveType::Target(0, 0)));
+        assert!(matches!(err, Err(LogicError::InvalidTarget { player: 0, index: 0 })));
+        let err = validate_move(&state, &Move::new(0, MoveType::Draw));
+        assert!(matches!(err, Err(LogicError::WrongPhase { player: 0, phase: PhaseType::React })));
+    }
+
+    #[test]
+    fn test_hand_limit() {
+        let mut board = Board::new_base_game(2);
+        let limit = Rules::default().hand_limit;
+        board.players[0].hand = board.deck.drain(0..limit + 1).collect();
+
+        let state = GameState::new(&board, &PhaseType::Play);
+        let (_, state) = resolve_move(&state, &Move::new(0, MoveType::Draw)).unwrap();
+        assert!(state.turn == 0, "Turn should not pass while over the hand limit.");
+
+        let err = validate_move(&state, &Move::new(0, MoveType::Pass));
+        assert!(matches!(err, Err(LogicError::HandLimitExceeded { player: 0, hand_size: 9, limit: 7 })));
+
+        let (_, state) = resolve_move(&state, &Move::new(0, MoveType::Discard(0))).unwrap();
+        let (_, state) = resolve_move(&state, &Move::new(0, MoveType::Discard(0))).unwrap();
+        assert!(state.turn == 1 && state.board.players[0].hand.len() == limit);
+    }
+
+    #[test]
+    fn test_legends() {
+        let mut board = Board::new_base_game(2);
+        board.players[1].stable = vec![card_from_name("Unicorn of Plenty").unwrap()];
+        board.players[0].hand = vec![card_from_name("Frenzied Unicorn").unwrap(), card_from_name("Basic Unicorn").unwrap()];
+        let deck_size = board.deck.len();
+
+        // Unicorn of Plenty gives everyone a second card at the beginning of their turn.
+        let state = GameState::new(&board, &PhaseType::Draw);
+        let (_, state) = resolve_move(&state, &Move::new(0, MoveType::Draw)).unwrap();
+        assert!(state.board.players[0].hand.len() == 4 && state.board.deck.len() == deck_size - 2);
+        let (action, _) = resolve_move(&state, &Move::new(0, MoveType::Draw)).unwrap();
+        assert!(action.board.deck.len() == deck_size - 3, "Drawing instead of playing draws one card.");
+
+        // A second legend cannot join a stable that already has one.
+        let mut state = state;
+        state.board.players[0].stable.push(card_from_name("Iron Unicorn").unwrap());
+        assert!(!legal_actions(&state, 0).contains(&Move::new(0, MoveType::Play(0))));
+        assert!(legal_actions(&state, 0).contains(&Move::new(0, MoveType::Play(1))));
+    }
+
+    #[test]
+    fn test_modifiers() {
+        let slow_hooves = unregistered_card(r#"[{ "name": "Slow Hooves", "ctype": "Downgrade", "expansion": "Homebrew Downgrades",
+                                                 "modifiers": [{ "modifier": { "CannotPlay": { "Type": "Instant" } } }] }]"#,
+                                            "Slow Hooves");
+        let mut board = Board::new_base_game(2);
+        let first = take(&mut board, "Basic Unicorn");
+        let second = take(&mut board, "Basic Unicorn");
+        let poison = take(&mut board, "Unicorn Poison");
+        let neigh = take(&mut board, "Neigh");
+        board.players[0].stable = vec![take(&mut board, "Double Dutch")];
+        board.players[0].hand = vec![first, second];
+        board.players[1].stable = vec![take(&mut board, "Rainbow Aura"), slow_hooves];
+        board.players[1].hand = vec![neigh];
+
+        // Slow Hooves keeps player two from neighing, Double Dutch gives player one a second play.
+        let state = GameState::new(&board, &PhaseType::Play);
+        let (_, state) = resolve_move(&state, &Move::new(0, MoveType::Play(0))).unwrap();
+        assert!(legal_actions(&state, 1) == vec![Move::new(1, MoveType::Pass)]);
+        let err = validate_move(&state, &Move::new(1, MoveType::React(0)));
+        assert!(matches!(err, Err(LogicError::CardNotPlayable { player: 1, card: "Neigh" })));
+        let (_, state) = resolve_move(&state, &Move::new(1, MoveType::Pass)).unwrap();
+        assert!(state.phase == PhaseType::Play && state.turn == 0 && state.plays == 1);
+        let (_, state) = resolve_move(&state, &Move::new(0, MoveType::Play(0))).unwrap();
+        let (_, state) = resolve_move(&state, &Move::new(1, MoveType::Pass)).unwrap();
+        assert!(state.turn == 1 && state.plays == 0 && state.board.players[0].stable.count_card("Basic Unicorn") == 2);
+
+        // Rainbow Aura leaves poison nothing to destroy.
+        let mut state = state;
+        state.phase = PhaseType::Play;
+        state.board.players[1].stable.push(card_from_name("Basic Unicorn").unwrap());
+        state.board.players[0].stable.clear();
+        state.board.players[1].hand = vec![poison];
+        assert!(!legal_actions(&state, 1).contains(&Move::new(1, MoveType::Play(0))));
+    }
+}
@@ -0,0 +1,281 @@
+// Compact text form of moves, e.g. `P1 play "Unicorn Poison" -> P2#0; P2 Neigh; P1 SuperNeigh`.
+// Players are numbered from one and stable positions from zero, cards are named as in their `name`.
+//
+//   line   := move [-> P<player>#<index>]
+//   move   := P<player> verb
+//   verb   := draw | pass | accept | play <card> | react <card> | discard <card> | search <card>
+//           | target P<player>#<index> | <card>
+//   card   := "Card Name" | CardName | #<index>
+//
+// A bare card name is shorthand for reacting with that card. Searched cards are named or indexed
+// in the deck, every other card in the hand of the moving player.
+//
+// Targets are chosen once every reaction to a card is over, so a target written inline is no move of
+// its own: sequences hold it back and make it as the `target` move when its player is asked for one.
+// Single moves read by `parse_move` never carry one.
+
+// STD
+use std::fmt;
+
+// UU
+use crate::cards::Cards;
+use crate::game::{ActionEdge, Game};
+use crate::moves::{Move, MoveType};
+use crate::moves::next_to_act;
+use crate::state::{GameState, LogicError};
+
+#[derive(Debug, Clone)]
+pub enum NotationError {
+    Syntax(String),
+    UnknownCard { player: usize, card: String },
+    // Move `index` of a sequence could not be applied.
+    Logic { index: usize, error: LogicError },
+    // The sequence ended before the target written inline with move `index` was asked for.
+    TargetNotAsked { index: usize }
+}
+
+impl fmt::Display for NotationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            NotationError::Syntax(text) => write!(f, "cannot read move {:?}", text),
+            NotationError::UnknownCard { player, card } => write!(f, "P{} holds no card {:?}", player + 1, card),
+            NotationError::Logic { index, error } => write!(f, "move {}: {}", index + 1, error),
+            NotationError::TargetNotAsked { index } => write!(f, "move {}: its target was never asked for", index + 1)
+        }
+    }
+}
+
+impl std::error::Error for NotationError {}
+
+fn hand_card(state: &GameState, player: usize, idx: usize) -> String {
+    return match state.board.players.get(player).and_then(|p| p.hand.get(idx)) {
+        Some(card) => format!("{:?}", card.name()),
+        None => format!("#{}", idx)
+    };
+}
+
+fn deck_card(state: &GameState, idx: usize) -> String {
+    return match state.board.deck.get(idx) {
+        Some(card) => format!("{:?}", card.name()),
+        None => format!("#{}", idx)
+    };
+}
+
+/// Writes `mv` naming the cards it refers to in `state`.
+pub fn format_move(state: &GameState, mv: &Move) -> String {
+    let verb = match &mv.mtype {
+        MoveType::Draw => "draw".to_string(),
+        MoveType::Pass => "pass".to_string(),
+        MoveType::Play(idx) => format!("play {}", hand_card(state, mv.player, *idx)),
+        MoveType::React(idx) => format!("react {}", hand_card(state, mv.player, *idx)),
+        MoveType::Discard(idx) => format!("discard {}", hand_card(state, mv.player, *idx)),
+        MoveType::Target(p_idx, s_idx) => format!("target P{}#{}", p_idx + 1, s_idx),
+        MoveType::Search(idx) => format!("search {}", deck_card(state, *idx)),
+        MoveType::Accept => "accept".to_string()
+    };
+    return format!("P{} {}", mv.player + 1, verb);
+}
+
+// Edges already know their card, so no state is needed. Drawn and searched cards are not revealed.
+impl fmt::Display for ActionEdge {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let Some(mv) = &self.mv else {
+            return write!(f, "{} {:?}", format!("{:?}", self.atype).to_lowercase(), self.card.name());
+        };
+
+        let player = mv.player + 1;
+        match &mv.mtype {
+            MoveType::Draw => write!(f, "P{} draw", player),
+            MoveType::Pass => write!(f, "P{} pass", player),
+            MoveType::Play(_) => write!(f, "P{} play {:?}", player, self.card.name()),
+            MoveType::React(_) => write!(f, "P{} react {:?}", player, self.card.name()),
+            MoveType::Discard(_) => write!(f, "P{} discard {:?}", player, self.card.name()),
+            MoveType::Target(p_idx, s_idx) => write!(f, "P{} target P{}#{}", player, p_idx + 1, s_idx),
+            MoveType::Search(_) => write!(f, "P{} search", player),
+            MoveType::Accept => write!(f, "P{} accept", player)
+        }
+    }
+}
+
+fn parse_player(token: &str) -> Option<usize> {
+    let number: usize = token.strip_prefix('P').or(token.strip_prefix('p'))?.parse().ok()?;
+    return number.checked_sub(1);
+}
+
+// Card names compare without case or spaces so `SuperNeigh` finds "Super Neigh".
+fn squash(name: &str) -> String {
+    return name.chars().filter(|c| !c.is_whitespace() && *c != '"').flat_map(char::to_lowercase).collect();
+}
+
+// Position of the card named by `text` among `cards`.
+fn find_card(cards: &Cards, player: usize, text: &str) -> Result<usize, NotationError> {
+    if let Some(idx) = text.strip_prefix('#') {
+        return idx.parse().map_err(|_| NotationError::Syntax(text.to_string()));
+    }
+
+    let wanted = squash(text);
+    return cards.iter()
+                .position(|c| squash(c.name()) == wanted)
+                .ok_or(NotationError::UnknownCard { player, card: text.trim_matches('"').to_string() });
+}
+
+fn parse_card(state: &GameState, player: usize, text: &str) -> Result<usize, NotationError> {
+    return find_card(&state.board.players[player].hand, player, text);
+}
+
+/// Reads a single move, resolving card names against the hand of the moving player in `state`.
+pub fn parse_move(state: &GameState, text: &str) -> Result<Move, NotationError> {
+    let syntax = || NotationError::Syntax(text.to_string());
+    let text = text.trim();
+    let (player_token, rest) = text.split_once(char::is_whitespace).ok_or_else(syntax)?;
+    let player = parse_player(player_token).filter(|p| *p < state.board.players.len()).ok_or_else(syntax)?;
+
+    let rest = rest.trim();
+    let (verb, arg) = match rest.split_once(char::is_whitespace) {
+        Some((verb, arg)) => (verb.to_lowercase(), arg.trim()),
+        None => (rest.to_lowercase(), "")
+    };
+
+    let mtype = match (verb.as_str(), arg.is_empty()) {
+        ("draw", true) => MoveType::Draw,
+        ("pass", true) => MoveType::Pass,
+        ("accept", true) => MoveType::Accept,
+        ("search", false) => MoveType::Search(find_card(&state.board.deck, player, arg)?),
+        ("play", false) => MoveType::Play(parse_card(state, player, arg)?),
+        ("react", false) => MoveType::React(parse_card(state, player, arg)?),
+        ("discard", false) => MoveType::Discard(parse_card(state, player, arg)?),
+        ("target", false) => {
+            let (owner, index) = arg.split_once('#').ok_or_else(syntax)?;
+            let owner = parse_player(owner).ok_or_else(syntax)?;
+            MoveType::Target(owner, index.parse().map_err(|_| syntax())?)
+        },
+        _ => MoveType::React(parse_card(state, player, rest)?)
+    };
+    return Ok(Move::new(player, mtype));
+}
+
+// Makes the inline target written with move `index` once its player is asked for it.
+fn make_held_target(game: &mut Game, held: &mut Option<(usize, Move)>) -> Result<(), NotationError> {
+    let Some((index, target)) = held.take() else {
+        return Ok(());
+    };
+    match next_to_act(game.current_state()) {
+        Some((player, moves)) if player == target.player && moves.iter().any(|m| matches!(m.mtype, MoveType::Target(..))) => {
+            game.apply(&target).map_err(|error| NotationError::Logic { index, error })?;
+        },
+        _ => *held = Some((index, target))
+    }
+    return Ok(());
+}
+
+/// Applies a `;` separated sequence of moves to `game`, reading each against the state it is made in.
+// Nothing in the game reads sequences yet, they are for scenarios written by hand.
+#[allow(dead_code)]
+pub fn play_notation(game: &mut Game, text: &str) -> Result<(), NotationError> {
+    let mut held = None;
+    for (index, part) in text.split(';').map(str::trim).filter(|p| !p.is_empty()).enumerate() {
+        make_held_target(game, &mut held)?;
+        let (part, target) = match part.split_once("->") {
+            Some((part, target)) => (part, Some(target.trim())),
+            None => (part, None)
+        };
+
+        let mv = parse_move(game.current_state(), part)?;
+        if let Some(target) = target {
+            let target = parse_move(game.current_state(), &format!("P{} target {}", mv.player + 1, target))?;
+            held = Some((index, target));
+        }
+        game.apply(&mv).map_err(|error| NotationError::Logic { index, error })?;
+    }
+
+    make_held_target(game, &mut held)?;
+    return match held {
+        Some((index, _)) => Err(NotationError::TargetNotAsked { index }),
+        None => Ok(())
+    };
+}
+
+#[cfg(test)]
+mod NotationTest {
+    use super::*;
+    use crate::cards::*;
+    use crate::moves::{awaiting, legal_actions};
+    use crate::state::*;
+
+    fn scenario_board() -> Board {
+        let mut board = Board::new_base_game(2);
+        board.players[0].stable = vec![card_from_name("Unicorn Phoenix").unwrap()];
+        board.players[0].hand = vec![card_from_name("Neigh").unwrap(), card_from_name("Basic Unicorn").unwrap()];
+        board.players[1].hand = vec![card_from_name("Unicorn Poison").unwrap(), card_from_name("Super Neigh").unwrap()];
+        return board;
+    }
+
+    #[test]
+    fn test_format_and_parse() {
+        let mut state = GameState::new(&scenario_board(), &PhaseType::Play);
+        state.turn = 1;
+
+        let mv = Move::new(1, MoveType::Play(0));
+        assert!(format_move(&state, &mv) == "P2 play \"Unicorn Poison\"");
+        assert!(format_move(&state, &Move::new(0, MoveType::Target(1, 3))) == "P1 target P2#3");
+
+        for player in awaiting(&state) {
+            for mv in legal_actions(&state, player) {
+                assert!(parse_move(&state, &format_move(&state, &mv)).unwrap() == mv);
+            }
+        }
+
+        assert!(parse_move(&state, "p2 PLAY #0").unwrap() == mv);
+        assert!(parse_move(&state, "P2 SuperNeigh").unwrap() == Move::new(1, MoveType::React(1)));
+        assert!(matches!(parse_move(&state, "P1 play \"Unicorn Poison\""), Err(NotationError::UnknownCard { player: 0, .. })));
+        assert!(matches!(parse_move(&state, "P9 draw"), Err(NotationError::Syntax(_))));
+        assert!(matches!(parse_move(&state, "P1 target P2"), Err(NotationError::Syntax(_))));
+
+        let search = parse_move(&state, "P2 search \"Super Neigh\"").unwrap();
+        assert!(format_move(&state, &search) == "P2 search \"Super Neigh\"");
+        assert!(parse_move(&state, "P1 accept").unwrap() == Move::new(0, MoveType::Accept));
+    }
+
+    #[test]
+    fn test_scenario() {
+        let mut game = Game::new(&scenario_board(), false, None).unwrap();
+        play_notation(&mut game, "P1 draw; P1 play \"Basic Unicorn\"; P2 pass").unwrap();
+        play_notation(&mut game, "P2 draw; P2 play \"Unicorn Poison\"; P1 Neigh; P2 SuperNeigh; P1 pass").unwrap();
+        play_notation(&mut game, "P2 target P1#0").unwrap();
+
+        // Phoenix stays for a discard.
+        assert!(game.current_state().phase == PhaseType::Effect);
+        play_notation(&mut game, "P1 target P1#0; P1 discard #0").unwrap();
+        assert!(game.current_state().board.players[0].stable.has_card("Unicorn Phoenix"));
+
+        let err = play_notation(&mut game, "P2 draw; P2 draw");
+        assert!(matches!(err, Err(NotationError::Logic { index: 0, error: LogicError::NotYourTurn { .. } })));
+    }
+
+    #[test]
+    fn test_inline_target() {
+        let mut game = Game::new(&scenario_board(), false, None).unwrap();
+        play_notation(&mut game, "P1 draw; P1 play \"Basic Unicorn\"; P2 pass; P2 draw").unwrap();
+        let mut written_out = Game::restore(game.current_state(), game.rng().clone(), vec![]);
+
+        // The target waits for the reactions, just as if it were written as a move of its own.
+        play_notation(&mut game, "P2 play \"Unicorn Poison\" -> P1#0; P1 Neigh; P2 SuperNeigh; P1 pass").unwrap();
+        play_notation(&mut written_out, "P2 play \"Unicorn Poison\"; P1 Neigh; P2 SuperNeigh; P1 pass; P2 target P1#0").unwrap();
+        assert!(game.moves()[4..] == written_out.moves()[..]);
+
+        let mut game = Game::new(&scenario_board(), false, None).unwrap();
+        let err = play_notation(&mut game, "P1 draw -> P2#0");
+        assert!(matches!(err, Err(NotationError::TargetNotAsked { index: 0 })));
+        assert!(matches!(parse_move(game.current_state(), "P1 play \"Basic Unicorn\" -> P2#0"), Err(NotationError::UnknownCard { .. })));
+    }
+
+    #[test]
+    fn test_edge_display() {
+        let action = Action { card: card_from_name("Unicorn Poison").unwrap(), atype: ActionType::Discard, board: scenario_board() };
+        let mut edge = ActionEdge::from(&action);
+        assert!(edge.to_string() == "discard \"Unicorn Poison\"");
+
+        edge.mv = Some(Move::new(1, MoveType::Play(0)));
+        assert!(edge.to_string() == "P2 play \"Unicorn Poison\"");
+    }
+}
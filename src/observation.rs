@@ -0,0 +1,110 @@
+// What a single seat may know about a game. Stables, the discard pile and the chain being resolved
+// are public; hands are only revealed to their owner, and the deck and nursery only by size.
+// Full views reveal everything and are meant for after the fact, e.g. delayed spectator snapshots.
+
+// EXT
+use serde::{Deserialize, Serialize};
+
+// UU
+use crate::cards::Cards;
+use crate::state::{GameState, PhaseType, ResponseOp, Rules};
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct PlayerView {
+    pub stable: Vec<String>,
+    // Only filled in for the observing player.
+    pub hand: Option<Vec<String>>,
+    pub hand_size: usize
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Observation {
+    // None for an observer without a seat, who sees no hand at all.
+    pub viewer: Option<usize>,
+    pub turn: usize,
+    pub phase: PhaseType,
+    // Other players may neigh what is being resolved right now.
+    pub react_window: bool,
+    pub deck_size: usize,
+    // Deck from bottom to top, only in full views.
+    pub deck: Option<Vec<String>>,
+    pub nursery_size: usize,
+    pub discard: Vec<String>,
+    pub players: Vec<PlayerView>,
+    // Cards of the chain being resolved, oldest first.
+    pub chain: Vec<String>,
+    pub rules: Rules,
+    pub winner: Option<usize>
+}
+
+fn names(cards: &Cards) -> Vec<String> {
+    return cards.iter().map(|c| c.name().to_string()).collect();
+}
+
+/// Redacts `state` down to what `viewer` is allowed to see.
+pub fn observe(state: &GameState, viewer: Option<usize>) -> Observation {
+    return view(state, viewer, false);
+}
+
+/// Everything in `state`, every hand and the deck included.
+pub fn observe_full(state: &GameState) -> Observation {
+    return view(state, None, true);
+}
+
+fn view(state: &GameState, viewer: Option<usize>, full: bool) -> Observation {
+    let board = &state.board;
+    let players = board.players.iter().enumerate().map(|(p_idx, p)| PlayerView {
+        stable: names(&p.stable),
+        hand: if full || viewer == Some(p_idx) { Some(names(&p.hand)) } else { None },
+        hand_size: p.hand.len()
+    }).collect();
+
+    let react_window = state.phase == PhaseType::React
+                       && state.react_metadata.as_ref().is_some_and(|m| m.follow_up == ResponseOp::Neigh);
+    return Observation {
+        viewer,
+        turn: state.turn,
+        phase: state.phase.clone(),
+        react_window,
+        deck_size: board.deck.len(),
+        deck: if full { Some(names(&board.deck)) } else { None },
+        nursery_size: board.nursery.len(),
+        discard: names(&board.discard),
+        players,
+        chain: state.history.iter().map(|a| a.card.name().to_string()).collect(),
+        rules: state.rules.clone(),
+        winner: state.winner()
+    };
+}
+
+#[cfg(test)]
+mod ObservationTest {
+    use super::*;
+    use crate::cards::*;
+    use crate::state::*;
+
+    #[test]
+    fn test_observe_redacts_hands() {
+        let mut board = Board::new_base_game(3);
+        board.players[0].hand = vec![card_from_name("Neigh").unwrap()];
+        board.players[1].hand = vec![card_from_name("Unicorn Phoenix").unwrap(), card_from_name("Super Neigh").unwrap()];
+        board.players[2].stable = vec![card_from_name("Baby Unicorn").unwrap()];
+        let state = GameState::new(&board, &PhaseType::Play);
+
+        let seen = observe(&state, Some(1));
+        assert!(seen.players[1].hand == Some(vec!["Unicorn Phoenix".to_string(), "Super Neigh".to_string()]));
+        assert!(seen.players[0].hand.is_none() && seen.players[0].hand_size == 1);
+        assert!(seen.players[2].stable == vec!["Baby Unicorn".to_string()]);
+        assert!(seen.deck_size == board.deck.len() && !seen.react_window);
+
+        let text = serde_json::to_string(&observe(&state, None)).unwrap();
+        assert!(!text.contains("Phoenix") && !text.contains("\"Neigh\""));
+        assert!(text.contains("Baby Unicorn"));
+        assert!(seen.deck.is_none());
+
+        let full = observe_full(&state);
+        assert!(full.viewer.is_none() && full.players.iter().all(|p| p.hand.is_some()));
+        assert!(full.players[0].hand == Some(vec!["Neigh".to_string()]));
+        assert!(full.deck.as_ref().unwrap().len() == board.deck.len());
+    }
+}
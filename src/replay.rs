@@ -0,0 +1,210 @@
+// STD
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+// EXT
+use serde::{Deserialize, Serialize};
+
+// UU
+use crate::definitions::Set;
+use crate::game::Game;
+use crate::moves::Move;
+use crate::serialize::to_bytes;
+use crate::state::{Board, GameState, LogicError, Rules};
+
+pub const REPLAY_VERSION: u64 = 1;
+
+#[derive(Debug)]
+pub enum ReplayError {
+    Io(io::Error),
+    Json(serde_json::Error),
+    UnsupportedVersion(u64),
+    // Replay does not have one checksum per state.
+    Truncated { moves: usize, checksums: usize },
+    // Move `index` could not be applied.
+    Logic { index: usize, error: LogicError },
+    // State after move `index` differs from the recorded one, index 0 being the starting state.
+    Diverged { index: usize, expected: u64, found: u64 }
+}
+
+impl fmt::Display for ReplayError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ReplayError::Io(e) => write!(f, "could not access replay: {}", e),
+            ReplayError::Json(e) => write!(f, "malformed replay: {}", e),
+            ReplayError::UnsupportedVersion(v) => write!(f, "replay version {} is not supported", v),
+            ReplayError::Truncated { moves, checksums } => {
+                write!(f, "replay has {} moves but {} checksums, expected {}", moves, checksums, moves + 1)
+            },
+            ReplayError::Logic { index, error } => write!(f, "move {} could not be applied: {}", index, error),
+            ReplayError::Diverged { index, expected, found } => {
+                write!(f, "state {} diverged, expected checksum {:016x} but found {:016x}", index, expected, found)
+            }
+        }
+    }
+}
+
+impl std::error::Error for ReplayError {}
+
+/// Stable digest of a state, FNV-1a over its binary encoding.
+pub fn state_checksum(state: &GameState) -> u64 {
+    let bytes = to_bytes(state).expect("Game states always encode.");
+    let mut hash: u64 = 0xcbf29ce484222325;
+    for byte in bytes {
+        hash ^= byte as u64;
+        hash = hash.wrapping_mul(0x100000001b3);
+    }
+    return hash;
+}
+
+/// Everything needed to reproduce a game exactly: how it was set up and every move made.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Replay {
+    pub version: u64,
+    pub seed: [u8; 32],
+    // Card sets the game was dealt from, replays from before sets existed are base games.
+    #[serde(default = "base_game")]
+    pub sets: Vec<Set>,
+    pub player_count: u8,
+    pub rules: Rules,
+    pub moves: Vec<Move>,
+    // checksums[0] is the starting state, checksums[i + 1] the state after moves[i].
+    pub checksums: Vec<u64>
+}
+
+fn base_game() -> Vec<Set> {
+    return vec![Set::Base];
+}
+
+impl Replay {
+    pub fn new(seed: [u8; 32], sets: &[Set], player_count: u8, rules: &Rules) -> Self {
+        return Replay {
+            version: REPLAY_VERSION,
+            seed,
+            sets: sets.to_vec(),
+            player_count,
+            rules: rules.clone(),
+            moves: vec![],
+            checksums: vec![]
+        };
+    }
+
+    /// Sets up the recorded game, clearing anything recorded before.
+    pub fn start(&mut self) -> Result<Game, ReplayError> {
+        let game = self.new_game()?;
+        self.moves.clear();
+        self.checksums = vec![state_checksum(game.current_state())];
+        return Ok(game);
+    }
+
+    /// Applies `mv` to `game` and records it when legal.
+    pub fn record(&mut self, game: &mut Game, mv: &Move) -> Result<(), LogicError> {
+        game.apply(mv)?;
+        self.moves.push(mv.clone());
+        self.checksums.push(state_checksum(game.current_state()));
+        return Ok(());
+    }
+
+    /// Rebuilds every state of the game, starting state first, checking each against its checksum.
+    pub fn replay(&self) -> Result<Vec<GameState>, ReplayError> {
+        if self.checksums.len() != self.moves.len() + 1 {
+            return Err(ReplayError::Truncated { moves: self.moves.len(), checksums: self.checksums.len() });
+        }
+
+        let mut game = self.new_game()?;
+        let mut states = vec![game.current_state().clone()];
+        self.verify(0, game.current_state())?;
+
+        for (index, mv) in self.moves.iter().enumerate() {
+            game.apply(mv).map_err(|error| ReplayError::Logic { index, error })?;
+            self.verify(index + 1, game.current_state())?;
+            states.push(game.current_state().clone());
+        }
+        return Ok(states);
+    }
+
+    fn new_game(&self) -> Result<Game, ReplayError> {
+        let board = Board::new_with_sets(&self.sets, self.player_count);
+        return Game::new_with_rules(&board, true, Some(self.seed), &self.rules)
+                    .map_err(|error| ReplayError::Logic { index: 0, error });
+    }
+
+    fn verify(&self, index: usize, state: &GameState) -> Result<(), ReplayError> {
+        let (expected, found) = (self.checksums[index], state_checksum(state));
+        if expected != found {
+            return Err(ReplayError::Diverged { index, expected, found });
+        }
+        return Ok(());
+    }
+
+    pub fn to_json(&self) -> Result<String, ReplayError> {
+        return serde_json::to_string_pretty(self).map_err(ReplayError::Json);
+    }
+
+    pub fn from_json(text: &str) -> Result<Self, ReplayError> {
+        let replay: Replay = serde_json::from_str(text).map_err(ReplayError::Json)?;
+        if replay.version != REPLAY_VERSION {
+            return Err(ReplayError::UnsupportedVersion(replay.version));
+        }
+        return Ok(replay);
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), ReplayError> {
+        return fs::write(path, self.to_json()?).map_err(ReplayError::Io);
+    }
+
+    pub fn load(path: &Path) -> Result<Self, ReplayError> {
+        return Replay::from_json(&fs::read_to_string(path).map_err(ReplayError::Io)?);
+    }
+}
+
+#[cfg(test)]
+mod ReplayTest {
+    use super::*;
+    use crate::moves::{next_to_act, MoveType};
+
+    // Plays the first legal move of the first waiting player until the game stops.
+    fn recorded_game(seed: [u8; 32]) -> Replay {
+        let mut replay = Replay::new(seed, &[Set::Base], 2, &Rules::for_players(2));
+        let mut game = replay.start().unwrap();
+        for _ in 0..40 {
+            let Some((_, moves)) = next_to_act(game.current_state()) else {
+                break;
+            };
+            replay.record(&mut game, &moves[0]).unwrap();
+        }
+        return replay;
+    }
+
+    #[test]
+    fn test_replay_matches() {
+        let replay = recorded_game([3; 32]);
+        assert!(replay.moves.len() > 5);
+
+        let loaded = Replay::from_json(&replay.to_json().unwrap()).unwrap();
+        let states = loaded.replay().unwrap();
+        assert!(states.len() == replay.moves.len() + 1);
+        assert!(state_checksum(states.last().unwrap()) == *replay.checksums.last().unwrap());
+    }
+
+    #[test]
+    fn test_replay_diverges() {
+        let mut replay = recorded_game([3; 32]);
+        replay.checksums[4] ^= 1;
+        assert!(matches!(replay.replay(), Err(ReplayError::Diverged { index: 4, .. })));
+
+        // A different seed shuffles a different deck.
+        let mut replay = recorded_game([3; 32]);
+        replay.seed = [4; 32];
+        assert!(matches!(replay.replay(), Err(ReplayError::Diverged { index: 0, .. })));
+
+        let mut replay = recorded_game([3; 32]);
+        replay.moves[0] = Move::new(1, MoveType::Draw);
+        assert!(matches!(replay.replay(), Err(ReplayError::Logic { index: 0, error: LogicError::NotYourTurn { .. } })));
+
+        replay.checksums.pop();
+        assert!(matches!(replay.replay(), Err(ReplayError::Truncated { .. })));
+    }
+}
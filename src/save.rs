@@ -0,0 +1,232 @@
+// STD
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+// EXT
+use rand_chacha::ChaChaRng;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+// UU
+use crate::definitions::{load_file, DefinitionError, Set};
+use crate::game::Game;
+use crate::moves::Move;
+use crate::state::GameState;
+
+// Upgrades a raw save by exactly one version.
+type Migration = fn(&mut Value) -> Result<(), SaveError>;
+
+// MIGRATIONS[i] upgrades a save from version i + 1 to version i + 2.
+// Append one whenever the saved layout or card names change, which also bumps the version.
+const MIGRATIONS: &[Migration] = &[add_sources];
+
+pub const SAVE_VERSION: u64 = MIGRATIONS.len() as u64 + 1;
+
+/// Where games are saved unless told otherwise.
+pub const DEFAULT_SAVE: &str = "unicorns_save.json";
+
+#[derive(Debug)]
+pub enum SaveError {
+    Io(io::Error),
+    Json(serde_json::Error),
+    MissingVersion,
+    UnsupportedVersion(u64),
+    Cards(DefinitionError)
+}
+
+impl fmt::Display for SaveError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SaveError::Io(e) => write!(f, "could not access save: {}", e),
+            SaveError::Json(e) => write!(f, "malformed save: {}", e),
+            SaveError::MissingVersion => write!(f, "save has no format version"),
+            SaveError::UnsupportedVersion(v) => {
+                write!(f, "save format version {} is not supported, expected at most {}", v, SAVE_VERSION)
+            },
+            SaveError::Cards(e) => write!(f, "could not load the cards of the save: {}", e)
+        }
+    }
+}
+
+impl std::error::Error for SaveError {}
+
+/// Where the cards of a game come from, so a loaded game knows every card it names.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct CardSources {
+    pub sets: Vec<Set>,
+    // Definition files loaded on top of the built-in cards.
+    pub files: Vec<PathBuf>
+}
+
+impl Default for CardSources {
+    fn default() -> Self {
+        return CardSources { sets: vec![Set::Base], files: vec![] };
+    }
+}
+
+/// Everything needed to continue a game where it was left off.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct SaveGame {
+    pub version: u64,
+    pub sources: CardSources,
+    pub state: GameState,
+    pub rng: ChaChaRng,
+    pub moves: Vec<Move>
+}
+
+impl SaveGame {
+    pub fn from_game(game: &Game, sources: &CardSources) -> Self {
+        return SaveGame {
+            version: SAVE_VERSION,
+            sources: sources.clone(),
+            state: game.current_state().clone(),
+            rng: game.rng().clone(),
+            moves: game.moves().clone()
+        };
+    }
+
+    pub fn into_game(self) -> Game {
+        return Game::restore(&self.state, self.rng, self.moves);
+    }
+
+    pub fn to_json(&self) -> Result<String, SaveError> {
+        return serde_json::to_string_pretty(self).map_err(SaveError::Json);
+    }
+
+    /// Parses a save of any supported version, migrating it to the current one.
+    pub fn from_json(text: &str) -> Result<Self, SaveError> {
+        return migrate(serde_json::from_str(text).map_err(SaveError::Json)?, MIGRATIONS);
+    }
+}
+
+fn migrate(mut raw: Value, migrations: &[Migration]) -> Result<SaveGame, SaveError> {
+    let latest = migrations.len() as u64 + 1;
+    let version = raw.get("version").and_then(Value::as_u64).ok_or(SaveError::MissingVersion)?;
+    if version == 0 || version > latest {
+        return Err(SaveError::UnsupportedVersion(version));
+    }
+
+    for migration in &migrations[(version - 1) as usize..] {
+        migration(&mut raw)?;
+    }
+    raw["version"] = Value::from(latest);
+
+    // The state names its cards, so the files defining them are registered before it is read.
+    let sources: CardSources = serde_json::from_value(raw["sources"].clone()).map_err(SaveError::Json)?;
+    for file in &sources.files {
+        load_file(file).map_err(SaveError::Cards)?;
+    }
+    return serde_json::from_value(raw).map_err(SaveError::Json);
+}
+
+// Version 1 saves predate expansions and card files, they are base games.
+fn add_sources(raw: &mut Value) -> Result<(), SaveError> {
+    raw["sources"] = serde_json::to_value(CardSources::default()).map_err(SaveError::Json)?;
+    return Ok(());
+}
+
+pub fn save_game(game: &Game, sources: &CardSources, path: &Path) -> Result<(), SaveError> {
+    let text = SaveGame::from_game(game, sources).to_json()?;
+    return fs::write(path, text).map_err(SaveError::Io);
+}
+
+pub fn load_game(path: &Path) -> Result<SaveGame, SaveError> {
+    let text = fs::read_to_string(path).map_err(SaveError::Io)?;
+    return SaveGame::from_json(&text);
+}
+
+#[cfg(test)]
+mod SaveTest {
+    use super::*;
+    use crate::cards::*;
+    use crate::moves::MoveType;
+    use crate::state::*;
+    use rand::RngCore;
+
+    fn started_game() -> Game {
+        let mut game = Game::new(&Board::new_base_game(2), true, Some([7; 32])).unwrap();
+        game.apply(&Move::new(0, MoveType::Draw)).unwrap();
+        return game;
+    }
+
+    #[test]
+    fn test_save_and_load() {
+        let game = started_game();
+        let sources = CardSources { sets: vec![Set::Base, Set::Dragons], files: vec![] };
+        let path = std::env::temp_dir().join(format!("uu_save_test_{}.json", std::process::id()));
+        save_game(&game, &sources, &path).unwrap();
+        let save = load_game(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+        assert!(save.sources == sources);
+        let loaded = save.into_game();
+
+        let (state, loaded_state) = (game.current_state(), loaded.current_state());
+        assert!(loaded.moves() == game.moves());
+        assert!(loaded_state.phase == state.phase && loaded_state.turn == state.turn);
+        assert!(loaded_state.rules == state.rules);
+        assert!(loaded_state.board.deck.len() == state.board.deck.len());
+        assert!(loaded_state.board.players[0].hand[0].name() == state.board.players[0].hand[0].name());
+        assert!(loaded.rng().clone().next_u64() == game.rng().clone().next_u64(), "Random stream should continue.");
+    }
+
+    #[test]
+    fn test_missing_card_file() {
+        let sources = CardSources { sets: vec![Set::Base], files: vec![PathBuf::from("no_such_cards.json")] };
+        let text = SaveGame::from_game(&started_game(), &sources).to_json().unwrap();
+        assert!(matches!(SaveGame::from_json(&text), Err(SaveError::Cards(_))));
+    }
+
+    #[test]
+    fn test_unsupported_version() {
+        let mut save = SaveGame::from_game(&started_game(), &CardSources::default());
+        save.version = SAVE_VERSION + 1;
+        let loaded = SaveGame::from_json(&save.to_json().unwrap());
+        assert!(matches!(loaded, Err(SaveError::UnsupportedVersion(v)) if v == SAVE_VERSION + 1));
+
+        let loaded = SaveGame::from_json("{}");
+        assert!(matches!(loaded, Err(SaveError::MissingVersion)));
+    }
+
+    // Pretend an older release called the poison card something else.
+    fn rename_poison(raw: &mut Value) -> Result<(), SaveError> {
+        if let Some(hand) = raw.pointer_mut("/state/board/players/0/hand") {
+            for card in hand.as_array_mut().unwrap() {
+                if card == "Poison Unicorn" {
+                    *card = Value::from("Unicorn Poison");
+                }
+            }
+        }
+        return Ok(());
+    }
+
+    // A save as version 1 wrote it, before card sources were kept.
+    fn first_version(save: SaveGame) -> Value {
+        let mut raw = serde_json::to_value(save).unwrap();
+        raw.as_object_mut().unwrap().remove("sources");
+        raw["version"] = Value::from(1);
+        return raw;
+    }
+
+    #[test]
+    fn test_migration() {
+        let sources = CardSources { sets: vec![Set::Dragons], files: vec![] };
+        let raw = first_version(SaveGame::from_game(&started_game(), &sources));
+        let loaded = migrate(raw, MIGRATIONS).unwrap();
+        assert!(loaded.version == SAVE_VERSION && loaded.sources == CardSources::default());
+
+        let mut save = SaveGame::from_game(&started_game(), &CardSources::default());
+        save.state.board.players[0].hand = vec![card_from_name("Unicorn Poison").unwrap()];
+        let mut raw = first_version(save);
+        raw["state"]["board"]["players"][0]["hand"][0] = Value::from("Poison Unicorn");
+
+        let loaded = migrate(raw.clone(), MIGRATIONS);
+        assert!(matches!(loaded, Err(SaveError::Json(_))), "Current version does not know the old name.");
+
+        // A further format version that renamed the card upgrades the version one save.
+        let loaded = migrate(raw, &[add_sources, rename_poison]).unwrap();
+        assert!(loaded.version == 3);
+        assert!(loaded.state.board.players[0].hand[0].name() == "Unicorn Poison");
+    }
+}
@@ -0,0 +1,113 @@
+// STD
+use std::fmt;
+
+// EXT
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+#[derive(Debug)]
+pub enum SerializeError {
+    Json(serde_json::Error),
+    Encode(bincode::error::EncodeError),
+    Decode(bincode::error::DecodeError)
+}
+
+impl fmt::Display for SerializeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SerializeError::Json(e) => write!(f, "json: {}", e),
+            SerializeError::Encode(e) => write!(f, "binary encode: {}", e),
+            SerializeError::Decode(e) => write!(f, "binary decode: {}", e)
+        }
+    }
+}
+
+impl std::error::Error for SerializeError {}
+
+// The game itself only encodes so far, the decoding half is kept for library users.
+#[allow(dead_code)]
+pub fn to_json<T: Serialize>(value: &T) -> Result<String, SerializeError> {
+    return serde_json::to_string(value).map_err(SerializeError::Json);
+}
+
+#[allow(dead_code)]
+pub fn from_json<T: DeserializeOwned>(text: &str) -> Result<T, SerializeError> {
+    return serde_json::from_str(text).map_err(SerializeError::Json);
+}
+
+/// Compact binary encoding, meant for transmitting or storing many states.
+pub fn to_bytes<T: Serialize>(value: &T) -> Result<Vec<u8>, SerializeError> {
+    return bincode::serde::encode_to_vec(value, bincode::config::standard()).map_err(SerializeError::Encode);
+}
+
+#[allow(dead_code)]
+pub fn from_bytes<T: DeserializeOwned>(bytes: &[u8]) -> Result<T, SerializeError> {
+    let (value, _) = bincode::serde::decode_from_slice(bytes, bincode::config::standard())
+                                    .map_err(SerializeError::Decode)?;
+    return Ok(value);
+}
+
+#[cfg(test)]
+mod SerializeTest {
+    use super::*;
+    use crate::cards::*;
+    use crate::game::ActionEdge;
+    use crate::moves::{Move, MoveType};
+    use crate::state::*;
+
+    // Boards are compared through their encoding since cards have no equality.
+    fn assert_round_trip<T: Serialize + DeserializeOwned>(value: &T) {
+        let json = to_json(value).unwrap();
+        assert!(to_json(&from_json::<T>(&json).unwrap()).unwrap() == json);
+
+        let bytes = to_bytes(value).unwrap();
+        assert!(to_bytes(&from_bytes::<T>(&bytes).unwrap()).unwrap() == bytes);
+    }
+
+    #[test]
+    fn test_card_round_trip() {
+        for card in all_cards() {
+            let json = to_json(&card).unwrap();
+            let loaded: Box<dyn Card> = from_json(&json).unwrap();
+            assert!(loaded.name() == card.name());
+            assert!(loaded.ctype() == card.ctype());
+
+            let loaded: Box<dyn Card> = from_bytes(&to_bytes(&card).unwrap()).unwrap();
+            assert!(loaded.name() == card.name());
+        }
+    }
+
+    #[test]
+    fn test_unknown_card() {
+        let loaded = from_json::<Box<dyn Card>>("\"Not A Unicorn\"");
+        assert!(matches!(loaded, Err(SerializeError::Json(_))));
+    }
+
+    #[test]
+    fn test_state_round_trip() {
+        let mut board = Board::new_base_game(3);
+        board.players[0].hand = all_cards();
+        board.players[1].stable = all_cards();
+        board.discard = all_cards();
+
+        let action = Action { card: card_from_name("Unicorn Poison").unwrap(), atype: ActionType::Discard, board: board.clone() };
+        let reaction = ReactAction { effect_action: action.clone(), follow_up: Some(ResponseOp::Destroy), response_user: vec![0] };
+        let mut state = GameState::new(&board, &PhaseType::React);
+        state.turn = 2;
+        state.history = vec![action.clone()];
+        state.react_metadata = Option::<ReactMetadata>::from(&reaction);
+
+        let mut edge = ActionEdge::from(&action);
+        edge.mv = Some(Move::new(2, MoveType::Target(1, 3)));
+
+        assert_round_trip(&board);
+        assert_round_trip(&state);
+        assert_round_trip(&reaction);
+        assert_round_trip(&edge);
+
+        let loaded: GameState = from_json(&to_json(&state).unwrap()).unwrap();
+        assert!(loaded.turn == 2 && loaded.phase == PhaseType::React);
+        assert!(loaded.board.players[1].stable.has_card("Unicorn Phoenix"));
+        assert!(loaded.react_metadata.unwrap().follow_up == ResponseOp::Destroy);
+    }
+}
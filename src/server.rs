@@ -0,0 +1,475 @@
+// Hosts games over TCP. Every message is one JSON object per line, tagged by its `type`:
+//
+//   server -> client: welcome, your_move, event, timeout, error, game_over
+//                     for spectators spectating, snapshot
+//                     and for the lobby table_created, tables, joined, bot_seated, watching, left
+//   client -> server: move
+//                     and for the lobby create_table, list_tables, join, add_bot, watch, leave
+//
+// Seats only ever receive their own observation, and events are shared with everyone.
+// Spectators see events with no hand revealed, and full snapshots only as their `SnapshotPolicy` allows.
+// A seat that disconnects, or lets a timer run out, plays `pass` when it can and its first legal move otherwise.
+
+// STD
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::net::{Shutdown, TcpListener, TcpStream};
+use std::sync::mpsc::{channel, Receiver, RecvTimeoutError, Sender};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+// EXT
+use serde::{Deserialize, Serialize};
+
+// UU
+use crate::agent::Agent;
+use crate::definitions::Set;
+use crate::game::Game;
+use crate::moves::{next_to_act, validate_move, Move, MoveType};
+use crate::lobby::TableInfo;
+use crate::observation::{observe, observe_full, Observation};
+use crate::state::{GameState, Rules};
+use crate::terminal::describe_result;
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ServerMessage {
+    Welcome { seat: usize, players: usize },
+    YourMove { observation: Observation, moves: Vec<Move>, timeout_ms: Option<u64> },
+    // Sent to every seat after each move, with that seat's own observation.
+    Event { text: String, observation: Observation },
+    // The seat took too long, `mv` was played for it.
+    Timeout { mv: Move },
+    Error { message: String },
+    GameOver { winner: Option<usize>, result: String },
+    // First message to a new spectator, the public view of the game so far.
+    Spectating { observation: Observation },
+    // Full view of the state after `after_moves` moves.
+    Snapshot { after_moves: usize, observation: Observation },
+    TableCreated { table: usize },
+    Tables { tables: Vec<TableInfo> },
+    Joined { table: usize, seat: usize },
+    BotSeated { table: usize, seat: usize },
+    Watching { table: usize },
+    Left { table: usize }
+}
+
+/// When a spectator gets to see the hidden information of a game.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum SnapshotPolicy {
+    #[default]
+    Never,
+    // Trailing the game by `moves` moves, and once more at the end.
+    Delayed { moves: usize },
+    AtEnd
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ClientMessage {
+    Move { mv: Move },
+    // Rules default to the usual ones for the table size, the seed to one picked by the lobby and the sets to the
+    // ones the lobby was opened with.
+    CreateTable { players: u8, seed: Option<u64>, rules: Option<Rules>, sets: Option<Vec<Set>> },
+    ListTables,
+    // Takes `seat`, or the first free one.
+    Join { table: usize, seat: Option<usize>, name: Option<String> },
+    AddBot { table: usize, agent: String },
+    Watch { table: usize, #[serde(default)] snapshots: SnapshotPolicy },
+    Leave
+}
+
+/// What the reader of a seat's connection hands to the table.
+#[derive(Debug)]
+pub enum Inbound {
+    Message(usize, ClientMessage),
+    Malformed(usize, String),
+    Closed(usize),
+    // A spectator to add to the table.
+    Watch(TcpStream, SnapshotPolicy)
+}
+
+pub enum TableSeat {
+    Remote(TcpStream),
+    Bot(Box<dyn Agent>),
+    // A remote seat whose connection was lost.
+    Gone
+}
+
+#[derive(Clone, Debug)]
+pub struct ServerConfig {
+    // How long a seat may think about neighing.
+    pub react_timeout: Duration,
+    // How long a seat may think about any other move, None to wait forever.
+    pub move_timeout: Option<Duration>
+}
+
+impl Default for ServerConfig {
+    fn default() -> Self {
+        return ServerConfig { react_timeout: Duration::from_secs(10), move_timeout: None };
+    }
+}
+
+// Lines go out in a single write so threads sharing a connection do not interleave them.
+pub fn send<W: Write>(writer: &mut W, message: &ServerMessage) -> io::Result<()> {
+    let mut line = serde_json::to_string(message).map_err(io::Error::other)?;
+    line.push('\n');
+    writer.write_all(line.as_bytes())?;
+    return writer.flush();
+}
+
+/// Forwards every line `seat` sends to `inbox` until the connection closes.
+pub fn spawn_reader<R: Read + Send + 'static>(seat: usize, reader: R, inbox: Sender<Inbound>) -> JoinHandle<()> {
+    return thread::spawn(move || {
+        for line in BufReader::new(reader).lines() {
+            let Ok(line) = line else {
+                break;
+            };
+            if line.trim().is_empty() {
+                continue;
+            }
+            let inbound = match serde_json::from_str(&line) {
+                Ok(message) => Inbound::Message(seat, message),
+                Err(e) => Inbound::Malformed(seat, e.to_string())
+            };
+            if inbox.send(inbound).is_err() {
+                return;
+            }
+        }
+        let _ = inbox.send(Inbound::Closed(seat));
+    });
+}
+
+/// The move made for a seat that cannot or did not answer.
+pub fn default_move(moves: &[Move]) -> Move {
+    return moves.iter().find(|m| m.mtype == MoveType::Pass).unwrap_or(&moves[0]).clone();
+}
+
+// Messages to a seat that cannot be delivered mean the seat is gone.
+fn send_to(seats: &mut [TableSeat], seat: usize, message: &ServerMessage) {
+    if let TableSeat::Remote(stream) = &mut seats[seat] {
+        if send(stream, message).is_err() {
+            seats[seat] = TableSeat::Gone;
+        }
+    }
+}
+
+struct Spectator {
+    stream: TcpStream,
+    snapshots: SnapshotPolicy
+}
+
+// Sends each spectator what `messages` picks for it, dropping those who cannot be reached.
+fn broadcast(spectators: &mut Vec<Spectator>, messages: impl Fn(&Spectator) -> Vec<ServerMessage>) {
+    spectators.retain_mut(|spectator| {
+        return messages(spectator).iter().all(|m| send(&mut spectator.stream, m).is_ok());
+    });
+}
+
+// Spectators of a table, with the full views delayed snapshots are taken from.
+struct Audience {
+    spectators: Vec<Spectator>,
+    // history[i] is the full view after i moves.
+    history: Vec<Observation>
+}
+
+impl Audience {
+    fn new(state: &GameState) -> Self {
+        return Audience { spectators: vec![], history: vec![observe_full(state)] };
+    }
+
+    fn add(&mut self, mut stream: TcpStream, snapshots: SnapshotPolicy, state: &GameState) {
+        if send(&mut stream, &ServerMessage::Spectating { observation: observe(state, None) }).is_ok() {
+            self.spectators.push(Spectator { stream, snapshots });
+        }
+    }
+
+    fn event(&mut self, text: &str, state: &GameState) {
+        self.history.push(observe_full(state));
+        let (played, history) = (self.history.len() - 1, &self.history);
+        let public = ServerMessage::Event { text: text.to_string(), observation: observe(state, None) };
+        broadcast(&mut self.spectators, |spectator| {
+            let mut messages = vec![public.clone()];
+            if let SnapshotPolicy::Delayed { moves } = spectator.snapshots {
+                if played >= moves {
+                    messages.push(ServerMessage::Snapshot { after_moves: played - moves, observation: history[played - moves].clone() });
+                }
+            }
+            return messages;
+        });
+    }
+
+    fn game_over(&mut self, over: &ServerMessage) {
+        let (played, last) = (self.history.len() - 1, self.history.last().unwrap().clone());
+        broadcast(&mut self.spectators, |spectator| {
+            let mut messages = vec![];
+            if spectator.snapshots != SnapshotPolicy::Never {
+                messages.push(ServerMessage::Snapshot { after_moves: played, observation: last.clone() });
+            }
+            messages.push(over.clone());
+            return messages;
+        });
+    }
+}
+
+fn handle_stray(seats: &mut [TableSeat], audience: &mut Audience, state: &GameState, inbound: Inbound) {
+    match inbound {
+        Inbound::Message(seat, ClientMessage::Move { .. }) => {
+            send_to(seats, seat, &ServerMessage::Error { message: "no move is expected from you".to_string() });
+        },
+        Inbound::Message(seat, _) => {
+            send_to(seats, seat, &ServerMessage::Error { message: "only moves are accepted during a game".to_string() });
+        },
+        Inbound::Malformed(seat, e) => send_to(seats, seat, &ServerMessage::Error { message: format!("malformed message: {}", e) }),
+        Inbound::Closed(seat) => seats[seat] = TableSeat::Gone,
+        Inbound::Watch(stream, snapshots) => audience.add(stream, snapshots, state)
+    }
+}
+
+// Waits for `player` to send one of `moves`, answering everyone else who writes in the meantime.
+fn wait_for_move(game: &Game, seats: &mut [TableSeat], audience: &mut Audience, inbox: &Receiver<Inbound>, player: usize,
+                 moves: &[Move], timeout: Option<Duration>) -> Move {
+    let deadline = timeout.map(|t| Instant::now() + t);
+    loop {
+        if !matches!(seats[player], TableSeat::Remote(_)) {
+            return default_move(moves);
+        }
+
+        let inbound = match deadline {
+            Some(deadline) => inbox.recv_timeout(deadline.saturating_duration_since(Instant::now())),
+            None => inbox.recv().map_err(|_| RecvTimeoutError::Disconnected)
+        };
+        match inbound {
+            Ok(Inbound::Message(seat, ClientMessage::Move { mv })) if seat == player => {
+                if moves.contains(&mv) {
+                    return mv;
+                }
+                let message = match validate_move(game.current_state(), &mv) {
+                    Err(e) => e.to_string(),
+                    Ok(_) => format!("P{} may not make that move", seat + 1)
+                };
+                send_to(seats, seat, &ServerMessage::Error { message });
+            },
+            Ok(inbound) => handle_stray(seats, audience, game.current_state(), inbound),
+            Err(RecvTimeoutError::Timeout) => {
+                let mv = default_move(moves);
+                send_to(seats, player, &ServerMessage::Timeout { mv: mv.clone() });
+                return mv;
+            },
+            Err(RecvTimeoutError::Disconnected) => return default_move(moves)
+        }
+    }
+}
+
+/// Plays `game` to the end with `seats`, whose connections report to `inbox`. Returns the winner.
+pub fn run_table(game: &mut Game, seats: &mut [TableSeat], inbox: &Receiver<Inbound>, config: &ServerConfig) -> Option<usize> {
+    let mut audience = Audience::new(game.current_state());
+    while let Ok(inbound) = inbox.try_recv() {
+        handle_stray(seats, &mut audience, game.current_state(), inbound);
+    }
+
+    while let Some((player, moves)) = next_to_act(game.current_state()) {
+        let observation = observe(game.current_state(), Some(player));
+        let timeout = if observation.react_window { Some(config.react_timeout) } else { config.move_timeout };
+
+        let mv = match &mut seats[player] {
+            TableSeat::Bot(agent) => {
+                let mv = moves[agent.choose(game.current_state(), player, &moves).min(moves.len() - 1)].clone();
+                if agent.forfeited() {
+                    seats[player] = TableSeat::Gone;
+                }
+                mv
+            },
+            TableSeat::Gone => default_move(&moves),
+            TableSeat::Remote(_) => {
+                let timeout_ms = timeout.map(|t| t.as_millis() as u64);
+                send_to(seats, player, &ServerMessage::YourMove { observation, moves: moves.clone(), timeout_ms });
+                wait_for_move(game, seats, &mut audience, inbox, player, &moves, timeout)
+            }
+        };
+
+        // Only listed moves get this far, so applying them cannot fail.
+        game.apply(&mv).expect("Legal moves always apply.");
+        let text = game.last_action().map(|e| e.to_string()).unwrap_or_default();
+        for seat in 0..seats.len() {
+            let observation = observe(game.current_state(), Some(seat));
+            send_to(seats, seat, &ServerMessage::Event { text: text.clone(), observation });
+        }
+        audience.event(&text, game.current_state());
+
+        // Answer anything that arrived while nobody was being asked.
+        while let Ok(inbound) = inbox.try_recv() {
+            handle_stray(seats, &mut audience, game.current_state(), inbound);
+        }
+    }
+
+    let state = game.current_state();
+    let over = ServerMessage::GameOver { winner: state.winner(), result: describe_result(state) };
+    for seat in 0..seats.len() {
+        send_to(seats, seat, &over);
+    }
+    audience.game_over(&over);
+    return state.winner();
+}
+
+/// Waits for one connection per seat, in seat order, then plays `game` with them.
+pub fn serve(listener: &TcpListener, game: &mut Game, config: &ServerConfig) -> io::Result<Option<usize>> {
+    let players = game.current_state().board.players.len();
+    let (outbox, inbox) = channel();
+    let mut seats = vec![];
+    for seat in 0..players {
+        let (mut stream, _) = listener.accept()?;
+        // Moves are tiny and latency matters more than packet count.
+        stream.set_nodelay(true)?;
+        send(&mut stream, &ServerMessage::Welcome { seat, players })?;
+        spawn_reader(seat, stream.try_clone()?, outbox.clone());
+        seats.push(TableSeat::Remote(stream));
+    }
+    drop(outbox);
+
+    let winner = run_table(game, &mut seats, &inbox, config);
+    for seat in seats {
+        if let TableSeat::Remote(stream) = seat {
+            let _ = stream.shutdown(Shutdown::Both);
+        }
+    }
+    return Ok(winner);
+}
+
+#[cfg(test)]
+pub mod ServerTest {
+    use super::*;
+    use crate::cards::*;
+    use crate::state::*;
+    use std::net::SocketAddr;
+
+    // Answers a prompt, or stays silent with None.
+    pub type Script = Box<dyn FnMut(&ServerMessage) -> Option<ClientMessage> + Send>;
+
+    /// Connects to `addr` and answers prompts with `script` until the game ends, returning everything received.
+    pub fn scripted_client(addr: SocketAddr, mut script: Script) -> JoinHandle<Vec<ServerMessage>> {
+        let stream = TcpStream::connect(addr).unwrap();
+        stream.set_nodelay(true).unwrap();
+        return thread::spawn(move || {
+            let mut writer = stream.try_clone().unwrap();
+            let mut received = vec![];
+            for line in BufReader::new(stream).lines() {
+                let Ok(line) = line else {
+                    break;
+                };
+                let message: ServerMessage = serde_json::from_str(&line).unwrap();
+                if let Some(answer) = script(&message) {
+                    writeln!(writer, "{}", serde_json::to_string(&answer).unwrap()).unwrap();
+                }
+                let over = matches!(message, ServerMessage::GameOver { .. });
+                received.push(message);
+                if over {
+                    break;
+                }
+            }
+            return received;
+        });
+    }
+
+    pub fn first_move() -> Script {
+        return Box::new(|message| match message {
+            ServerMessage::YourMove { moves, .. } => Some(ClientMessage::Move { mv: moves[0].clone() }),
+            _ => None
+        });
+    }
+
+    pub fn scenario() -> Game {
+        let mut board = Board::new_base_game(2);
+        board.players[0].hand = vec![card_from_name("Basic Unicorn").unwrap()];
+        board.players[1].hand = vec![card_from_name("Neigh").unwrap()];
+        return Game::new(&board, true, None).unwrap();
+    }
+
+    fn seat_of(received: &[ServerMessage]) -> usize {
+        let Some(ServerMessage::Welcome { seat, .. }) = received.first() else {
+            panic!("First message should be a welcome.");
+        };
+        return *seat;
+    }
+
+    fn events(received: &[ServerMessage]) -> Vec<String> {
+        return received.iter().filter_map(|m| match m {
+            ServerMessage::Event { text, .. } => Some(text.clone()),
+            _ => None
+        }).collect();
+    }
+
+    #[test]
+    fn test_loopback_game() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let server = thread::spawn(move || {
+            let mut game = scenario();
+            let winner = serve(&listener, &mut game, &ServerConfig::default()).unwrap();
+            return (winner, game.moves().len());
+        });
+
+        let clients = vec![scripted_client(addr, first_move()), scripted_client(addr, first_move())];
+        let received: Vec<_> = clients.into_iter().map(|c| c.join().unwrap()).collect();
+        let (winner, move_count) = server.join().unwrap();
+
+        assert!(seat_of(&received[0]) == 0 && seat_of(&received[1]) == 1);
+        assert!(events(&received[0]) == events(&received[1]), "Events are public.");
+        assert!(events(&received[0]).len() == move_count);
+        assert!(events(&received[0])[1] == "P1 play \"Basic Unicorn\"");
+        assert!(events(&received[0])[2] == "P2 react \"Neigh\"");
+        for (seat, messages) in received.iter().enumerate() {
+            assert!(matches!(messages.last(), Some(ServerMessage::GameOver { winner: w, .. }) if *w == winner));
+            for message in messages {
+                let observation = match message {
+                    ServerMessage::YourMove { observation, moves, .. } => {
+                        assert!(moves.iter().all(|m| m.player == seat));
+                        observation
+                    },
+                    ServerMessage::Event { observation, .. } => observation,
+                    _ => continue
+                };
+                assert!(observation.players[seat].hand.is_some() && observation.players[1 - seat].hand.is_none());
+            }
+        }
+    }
+
+    #[test]
+    fn test_react_timeout_and_errors() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let server = thread::spawn(move || {
+            let mut game = scenario();
+            let config = ServerConfig { react_timeout: Duration::from_millis(50), move_timeout: None };
+            serve(&listener, &mut game, &config).unwrap();
+            return game;
+        });
+
+        let first = scripted_client(addr, first_move());
+        // Tries an illegal discard before every move and never answers a react window.
+        let mut pending = None;
+        let second = scripted_client(addr, Box::new(move |message| match message {
+            ServerMessage::YourMove { observation, .. } if observation.react_window => None,
+            ServerMessage::YourMove { moves, .. } => {
+                pending = Some(moves[0].clone());
+                Some(ClientMessage::Move { mv: Move::new(1, MoveType::Discard(9)) })
+            },
+            ServerMessage::Error { .. } => pending.take().map(|mv| ClientMessage::Move { mv }),
+            _ => None
+        }));
+
+        first.join().unwrap();
+        let received = second.join().unwrap();
+        let game = server.join().unwrap();
+
+        assert!(received.contains(&ServerMessage::Timeout { mv: Move::new(1, MoveType::Pass) }));
+        assert!(received.iter().any(|m| matches!(m, ServerMessage::Error { .. })));
+        assert!(events(&received)[2] == "P2 pass", "Timed out react window should pass.");
+        // The unicorn entered once the window closed, whatever the rest of the game did to it.
+        let mut replay = scenario();
+        for mv in &game.moves()[..3] {
+            replay.apply(mv).unwrap();
+        }
+        assert!(replay.current_state().board.players[0].stable.has_card("Basic Unicorn"));
+    }
+}
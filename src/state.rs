@@ -1,28 +1,97 @@
 // STD
-use std::any::{Any, TypeId};
 use std::fmt;
-use std::rc::Rc;
+
+// EXT
+use serde::{Deserialize, Serialize};
 
 // UU
 use crate::cards::*;
+use crate::definitions::{CardDef, Set};
+use crate::effects::Pending;
+use crate::modifiers;
+use crate::moves::Move;
 
 pub type History = Vec<Action>;
 
-macro_rules! add_cards {
-    ($deck:expr, $cls:ident, $num:expr ) => {
-        for _ in 0..$num {
-            $deck.push(Box::new($cls {}));
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum Zone {
+    Hand(usize),
+    Stable(usize),
+    Deck,
+    Nursery,
+    Discard
+}
+
+impl fmt::Display for Zone {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Zone::Hand(p) => write!(f, "player {}'s hand", p + 1),
+            Zone::Stable(p) => write!(f, "player {}'s stable", p + 1),
+            Zone::Deck => write!(f, "the deck"),
+            Zone::Nursery => write!(f, "the nursery"),
+            Zone::Discard => write!(f, "the discard pile")
         }
-    };
+    }
 }
 
 #[derive(Debug, Clone)]
 pub enum LogicError {
     DeckEmpty,
+    NurseryEmpty,
+    UnknownPlayer(usize),
+    // Graph index does not point at a node or edge of the game.
+    UnknownNode,
+    NotYourTurn { player: usize, turn: usize },
+    WrongPhase { player: usize, phase: PhaseType },
+    CardNotInZone { zone: Zone, index: usize },
+    InvalidTarget { player: usize, index: usize },
+    // A card outside the stables that the pending effect may not choose.
+    InvalidChoice { zone: Zone, index: usize },
+    CardNotPlayable { player: usize, card: &'static str },
+    CannotBeNeighed { card: &'static str },
+    HandLimitExceeded { player: usize, hand_size: usize, limit: usize },
+    // A response was given while nothing is waiting on one.
+    NoPendingAction,
+    GameOver { winner: usize },
+    // Move is not among the legal moves of the current state.
+    IllegalMove(Move),
     Unknown
 }
 
-#[derive(Debug, Clone)]
+impl fmt::Display for LogicError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LogicError::DeckEmpty => write!(f, "the deck is empty"),
+            LogicError::NurseryEmpty => write!(f, "the nursery has no baby unicorns left"),
+            LogicError::UnknownPlayer(p) => write!(f, "there is no player {}", p + 1),
+            LogicError::UnknownNode => write!(f, "the game graph has no such node or edge"),
+            LogicError::NotYourTurn { player, turn } => {
+                write!(f, "player {} cannot act now, waiting on player {}", player + 1, turn + 1)
+            },
+            LogicError::WrongPhase { player, phase } => {
+                write!(f, "player {} cannot make that move during the {:?} phase", player + 1, phase)
+            },
+            LogicError::CardNotInZone { zone, index } => write!(f, "no card at position {} of {}", index, zone),
+            LogicError::InvalidTarget { player, index } => {
+                write!(f, "card {} of player {}'s stable is not a valid target", index, player + 1)
+            },
+            LogicError::InvalidChoice { zone, index } => write!(f, "card {} of {} cannot be chosen", index, zone),
+            LogicError::CardNotPlayable { player, card } => write!(f, "player {} cannot play {} now", player + 1, card),
+            LogicError::CannotBeNeighed { card } => write!(f, "{} cannot be neighed", card),
+            LogicError::HandLimitExceeded { player, hand_size, limit } => {
+                write!(f, "player {} holds {} cards and must discard down to {}", player + 1, hand_size, limit)
+            },
+            LogicError::NoPendingAction => write!(f, "there is nothing to respond to"),
+            LogicError::GameOver { winner } => write!(f, "the game is over, player {} won", winner + 1),
+            LogicError::IllegalMove(mv) => write!(f, "{:?} is not a legal move", mv),
+            LogicError::Unknown => write!(f, "unknown logic error")
+        }
+    }
+}
+
+impl std::error::Error for LogicError {}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Player {
     pub hand: Cards,
     pub stable: Cards
@@ -37,7 +106,7 @@ impl Player {
     }
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Board {
     pub players: Vec<Player>,
     pub deck: Cards,
@@ -47,24 +116,41 @@ pub struct Board {
 
 impl Board {
     pub fn new_base_game(player_count: u8) -> Board {
+        return Board::new_with_sets(&[Set::Base], player_count);
+    }
+
+    /// Board with the decks and nurseries of every set in `sets` shuffled together.
+    pub fn new_with_sets(sets: &[Set], player_count: u8) -> Board {
+        let mut defs = vec![];
+        for (idx, set) in sets.iter().enumerate() {
+            // Choosing a set twice does not double its cards.
+            if !sets[..idx].contains(set) {
+                defs.extend(set.cards());
+            }
+        }
+        return Board::from_definitions(&defs, player_count);
+    }
+
+    /// Board with every copy of `defs`, baby unicorns in the nursery and the rest in the deck.
+    pub fn from_definitions(defs: &[&'static CardDef], player_count: u8) -> Board {
         let mut deck: Cards = Vec::new();
+        let mut nursery: Cards = Vec::new();
 
         // Add number of cards
-        add_cards!(deck, BasicUnicorn, 2);
-        add_cards!(deck, Neigh, 3);
-        add_cards!(deck, SuperNeigh, 1);
-        add_cards!(deck, UnicornPhoenix, 1);
-        add_cards!(deck, UnicornPoison, 3);
+        for def in defs.iter() {
+            let pile = if def.ctype == CardType::BabyUnicorn { &mut nursery } else { &mut deck };
+            for _ in 0..def.copies {
+                pile.push(Box::new(DefinedCard::new(def)));
+            }
+        }
 
         assert!(player_count >= 2, "Must have atleast two players.");
 
         let mut players = Vec::new();
-        for i in 0..player_count {
+        for _ in 0..player_count {
             players.push(Player::new())
         }
 
-        let mut nursery: Cards = Vec::new();
-        add_cards!(nursery, BabyUnicorn, 3);
         let board = Board {
             players,
             deck,
@@ -75,8 +161,28 @@ impl Board {
         return  board;
     }
 
+    pub fn cards(&self, zone: &Zone) -> &Cards {
+        return match zone {
+            Zone::Hand(p) => &self.players[*p].hand,
+            Zone::Stable(p) => &self.players[*p].stable,
+            Zone::Deck => &self.deck,
+            Zone::Nursery => &self.nursery,
+            Zone::Discard => &self.discard
+        };
+    }
+
+    pub fn cards_mut(&mut self, zone: &Zone) -> &mut Cards {
+        return match zone {
+            Zone::Hand(p) => &mut self.players[*p].hand,
+            Zone::Stable(p) => &mut self.players[*p].stable,
+            Zone::Deck => &mut self.deck,
+            Zone::Nursery => &mut self.nursery,
+            Zone::Discard => &mut self.discard
+        };
+    }
+
     pub fn draw(&self) -> LogicResult {
-        if self.deck.len() <= 0 {
+        if self.deck.is_empty() {
             return Err(LogicError::DeckEmpty);
         }
 
@@ -97,13 +203,12 @@ impl Board {
     }
 
     /// Draws a specified card if applicable.
-    pub fn draw_specific_card<T: 'static + Card>(&self) -> LogicResult {
-        if self.deck.len() <= 0 {
+    pub fn draw_specific_card(&self, name: &str) -> LogicResult {
+        if self.deck.is_empty() {
             return Err(LogicError::DeckEmpty);
         }
 
-        let (c, new_deck) = self.deck.remove_one_card_with_type::<T>().unwrap();
-        if new_deck.len() != self.deck.len() {
+        if let Some((c, new_deck)) = self.deck.remove_one_card_named(name) {
             let new_board = Board {
                 players: self.players.clone(),
                 deck: new_deck.clone(),
@@ -124,7 +229,7 @@ impl Board {
 
 
 
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub enum ActionType {
     Place,
     Instant,
@@ -135,10 +240,14 @@ pub enum ActionType {
     Sacrifice,
     Revive,
     Draw,
+    // Card went from a stable back to a hand.
+    Return,
+    // Card was taken from the deck by choice.
+    Search,
     NoOp,
 }
 
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub enum PhaseType {
     GameStart,
     Play,
@@ -148,13 +257,25 @@ pub enum PhaseType {
     Draw
 }
 
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub enum ResponseOp {
     Discard,
-    Destroy
+    Destroy,
+    // Open window where players may answer the top of the history with an instant.
+    Neigh,
+    Sacrifice,
+    Steal,
+    Return,
+    Search,
+    // Which card of the hand to bring into the stable.
+    Bring,
+    // Whether to go ahead with an optional effect.
+    Confirm,
+    // Whether to let a card be removed or replace the removal.
+    Replace
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Action {
     pub card: Box<dyn Card>,
     pub atype: ActionType,
@@ -163,7 +284,7 @@ pub struct Action {
 
 // A ReactAction is a special kind of action
 // which requires a response from other players.
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ReactAction {
     pub effect_action: Action,
     pub follow_up: Option<ResponseOp>,
@@ -180,7 +301,7 @@ impl From<&Action> for ReactAction {
     }
 }
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct ReactMetadata {
     pub follow_up: ResponseOp,
     pub response_user: Vec<usize>
@@ -188,43 +309,121 @@ pub struct ReactMetadata {
 
 impl From<&ReactAction> for Option<ReactMetadata> {
     fn from(value: &ReactAction) -> Self {
-        if value.follow_up.is_none() {
-            return None;
-        }
-
+        let follow_up = value.follow_up.clone()?;
         return Some(ReactMetadata {
-            follow_up: value.follow_up.clone().unwrap(),
+            follow_up,
             response_user: value.response_user.clone()
         });
     }
 }
 
-pub type ReactResult = Result<Vec<ReactAction>, LogicError>;
 pub type LogicResult = Result<Option<Action>, LogicError>;
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Rules {
+    // Maximum cards a player may hold at the end of their turn.
+    pub hand_limit: usize,
+    // Unicorns a stable needs to win the game.
+    pub unicorns_to_win: usize
+}
+
+impl Rules {
+    pub fn for_players(player_count: usize) -> Self {
+        // Bigger tables race to fewer unicorns.
+        let unicorns_to_win = if player_count >= 6 { 6 } else { 7 };
+        return Rules { unicorns_to_win, ..Rules::default() };
+    }
+}
+
+impl Default for Rules {
+    fn default() -> Self {
+        return Rules { hand_limit: 7, unicorns_to_win: 7 };
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct GameState {
     pub board: Board,
     pub phase: PhaseType,
     pub react_metadata: Option<ReactMetadata>,
+    // Player whose turn it currently is.
+    pub turn: usize,
+    // Actions of the chain currently being resolved, oldest first.
+    pub history: History,
+    pub rules: Rules,
+    // Card effects still to resolve, the last one first.
+    #[serde(default)]
+    pub pending: Vec<Pending>,
+    // Plays the turn player already used this turn.
+    #[serde(default)]
+    pub plays: usize
 }
 
 impl GameState {
     pub fn new(board: &Board, phase: &PhaseType) -> Self {
-        return GameState { board: board.clone(), phase: phase.clone(), react_metadata: None };
+        return GameState {
+            board: board.clone(),
+            phase: phase.clone(),
+            react_metadata: None,
+            turn: 0,
+            history: vec![],
+            rules: Rules::for_players(board.players.len()),
+            pending: vec![],
+            plays: 0
+        };
+    }
+
+    /// State following this one within the same turn and rules.
+    pub fn child(&self, board: &Board, phase: &PhaseType) -> Self {
+        let mut next = GameState::new(board, phase);
+        next.turn = self.turn;
+        next.rules = self.rules.clone();
+        next.plays = self.plays;
+        return next;
+    }
+
+    /// First player whose stable holds enough unicorns to win.
+    pub fn winner(&self) -> Option<usize> {
+        return (0..self.board.players.len()).find(|p| modifiers::unicorns(&self.board, *p) >= modifiers::unicorns_to_win(&self.board, &self.rules, *p));
     }
 }
 
+#[cfg(test)]
 mod StateTest {
     use super::*;
 
     #[test]
     fn test_board_draw() {
         let drawn_card = Board::new_base_game(2)
-                            .draw_specific_card::<Neigh>()
+                            .draw_specific_card("Neigh")
                             .unwrap()
                             .unwrap()
                             .card;
         assert!(drawn_card.name() == "Neigh", "Drawn deck should match.")
     }
+
+    #[test]
+    fn test_winner() {
+        let mut board = Board::new_base_game(2);
+        for _ in 0..6 {
+            board.players[1].stable.push(card_from_name("Basic Unicorn").unwrap());
+        }
+        board.players[1].stable.push(card_from_name("Neigh").unwrap());
+
+        let mut state = GameState::new(&board, &PhaseType::Turn);
+        assert!(state.winner().is_none(), "Only unicorns count towards winning.");
+
+        state.board.players[1].stable.push(card_from_name("Baby Unicorn").unwrap());
+        assert!(state.winner() == Some(1));
+        assert!(Rules::for_players(6).unicorns_to_win == 6);
+    }
+
+    #[test]
+    fn test_new_with_sets() {
+        let base = Board::new_base_game(2);
+        let twice = Board::new_with_sets(&[Set::Base, Set::Base], 2);
+        assert!(twice.deck.len() == base.deck.len() && twice.nursery.len() == base.nursery.len());
+        let unknown = Board::new_with_sets(&[Set::Adventures], 2);
+        assert!(unknown.deck.is_empty() && unknown.nursery.is_empty());
+    }
 }
@@ -0,0 +1,253 @@
+// Which cards an effect may choose. A selector names a zone, whose zone it is relative to the player
+// resolving the effect, what kind of card it has to be and which cards are left out anyway, e.g.
+// "an Upgrade card in another player's stable" is
+//
+//   { "zone": "Stable", "owner": "Other", "cards": { "Type": "Upgrade" } }
+//
+// and "a Unicorn card in any player's stable, other than this one" is
+//
+//   { "cards": "Unicorn", "exclude": ["This"] }
+//
+// Left out fields match any card in any stable. Deck, discard pile and nursery belong to nobody, so
+// the owner only narrows down stables and hands. Dragons sit apart from the other cards in a stable,
+// a selector only reaches them when its filter asks for Dragon cards by type or they count as Unicorns
+// there, so cards that take "a card" such as Two-For-One never sacrifice or destroy a Dragon unless a
+// Dragon Bond is in play.
+
+// EXT
+use serde::{Deserialize, Serialize};
+
+// UU
+use crate::cards::{Card, CardType};
+use crate::modifiers;
+use crate::state::{Board, LogicError, Zone};
+
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub enum Area {
+    #[default]
+    Stable,
+    Hand,
+    Deck,
+    Discard,
+    Nursery
+}
+
+/// Whose stable or hand a card has to be in, relative to the player choosing.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub enum Owner {
+    #[default]
+    Any,
+    You,
+    Other
+}
+
+impl Owner {
+    pub fn allows(&self, chooser: usize, owner: usize) -> bool {
+        return match self {
+            Owner::Any => true,
+            Owner::You => owner == chooser,
+            Owner::Other => owner != chooser
+        };
+    }
+}
+
+/// What a card has to be.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub enum Filter {
+    #[default]
+    Any,
+    Unicorn,
+    Rainbow,
+    Legendary,
+    Type(CardType),
+    Named(String),
+    AnyOf(Vec<Filter>)
+}
+
+impl Filter {
+    /// Whether `card`, found in `zone` of `board`, matches.
+    pub fn matches(&self, board: &Board, zone: &Zone, card: &dyn Card) -> bool {
+        return match self {
+            Filter::Any => true,
+            Filter::Unicorn => modifiers::is_unicorn(board, zone, card),
+            Filter::Rainbow => card.is_rainbow(),
+            Filter::Legendary => card.is_legendary(),
+            Filter::Type(ctype) => card.ctype() == *ctype,
+            Filter::Named(name) => card.name() == name,
+            Filter::AnyOf(filters) => filters.iter().any(|f| f.matches(board, zone, card))
+        };
+    }
+
+    /// Whether this filter names `ctype` outright.
+    pub fn asks_for(&self, ctype: &CardType) -> bool {
+        return match self {
+            Filter::Type(t) => t == ctype,
+            Filter::AnyOf(filters) => filters.iter().any(|f| f.asks_for(ctype)),
+            _ => false
+        };
+    }
+}
+
+/// Cards left out even when they match.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum Exclude {
+    // The card whose effect is choosing.
+    This,
+    Matching(Filter)
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct Selector {
+    #[serde(default)]
+    pub zone: Area,
+    #[serde(default)]
+    pub owner: Owner,
+    #[serde(default)]
+    pub cards: Filter,
+    #[serde(default)]
+    pub exclude: Vec<Exclude>
+}
+
+impl From<Filter> for Selector {
+    fn from(cards: Filter) -> Self {
+        return Selector { cards, ..Selector::default() };
+    }
+}
+
+impl Selector {
+    /// Zones this selector looks through, seen from `chooser`.
+    fn zones(&self, board: &Board, chooser: usize) -> Vec<Zone> {
+        let players = (0..board.players.len()).filter(|p| self.owner.allows(chooser, *p));
+        return match self.zone {
+            Area::Stable => players.map(Zone::Stable).collect(),
+            Area::Hand => players.map(Zone::Hand).collect(),
+            Area::Deck => vec![Zone::Deck],
+            Area::Discard => vec![Zone::Discard],
+            Area::Nursery => vec![Zone::Nursery]
+        };
+    }
+
+    fn allows(&self, board: &Board, card: &dyn Card, at: &(Zone, usize), this: Option<&(Zone, usize)>) -> bool {
+        if !self.cards.matches(board, &at.0, card) {
+            return false;
+        }
+        let dragon = card.ctype() == CardType::Dragon && matches!(at.0, Zone::Stable(_));
+        if dragon && !self.cards.asks_for(&CardType::Dragon) && !modifiers::is_unicorn(board, &at.0, card) {
+            return false;
+        }
+        return !self.exclude.iter().any(|e| match e {
+            Exclude::This => this == Some(at),
+            Exclude::Matching(filter) => filter.matches(board, &at.0, card)
+        });
+    }
+
+    /// Every card `chooser` may pick, as zone and index. `this` is where the choosing card is, if anywhere.
+    pub fn targets(&self, board: &Board, chooser: usize, this: Option<&(Zone, usize)>) -> Vec<(Zone, usize)> {
+        let mut targets = vec![];
+        for zone in self.zones(board, chooser) {
+            for (idx, card) in board.cards(&zone).iter().enumerate() {
+                let at = (zone.clone(), idx);
+                if self.allows(board, card.as_ref(), &at, this) {
+                    targets.push(at);
+                }
+            }
+        }
+        return targets;
+    }
+
+    /// Explains why `chooser` may not pick the card at `index` of `zone`.
+    pub fn validate(&self, board: &Board, chooser: usize, this: Option<&(Zone, usize)>, zone: &Zone, index: usize) -> Result<(), LogicError> {
+        let card = board.cards(zone).get(index).ok_or(LogicError::CardNotInZone { zone: zone.clone(), index })?;
+        let at = (zone.clone(), index);
+        if self.zones(board, chooser).contains(zone) && self.allows(board, card.as_ref(), &at, this) {
+            return Ok(());
+        }
+        return match zone {
+            Zone::Stable(player) => Err(LogicError::InvalidTarget { player: *player, index }),
+            _ => Err(LogicError::InvalidChoice { zone: zone.clone(), index })
+        };
+    }
+}
+
+#[cfg(test)]
+mod TargetsTest {
+    use super::*;
+    use crate::cards::card_from_name;
+
+    fn board() -> Board {
+        let card = |name: &str| card_from_name(name).unwrap();
+        let mut board = Board::new_base_game(3);
+        board.players[0].stable = vec![card("Basic Unicorn"), card("Baby Unicorn")];
+        board.players[0].hand = vec![card("Neigh"), card("Unicorn Poison")];
+        board.players[1].stable = vec![card("Unicorn Phoenix"), card("Neigh")];
+        board.players[2].stable = vec![card("Basic Unicorn")];
+        return board;
+    }
+
+    fn selector(json: &str) -> Selector {
+        return serde_json::from_str(json).unwrap();
+    }
+
+    #[test]
+    fn test_stables() {
+        let board = board();
+        let unicorns = Selector::from(Filter::Unicorn);
+        assert!(unicorns.targets(&board, 0, None).len() == 4);
+
+        let theirs = selector(r#"{ "owner": "Other", "cards": "Unicorn" }"#);
+        assert!(theirs.targets(&board, 0, None) == vec![(Zone::Stable(1), 0), (Zone::Stable(2), 0)]);
+
+        let basic = selector(r#"{ "cards": { "Type": "BasicUnicorn" } }"#);
+        assert!(basic.targets(&board, 1, None) == vec![(Zone::Stable(0), 0), (Zone::Stable(2), 0)]);
+
+        let mine = selector(r#"{ "owner": "You", "cards": { "AnyOf": ["Unicorn", { "Type": "Instant" }] } }"#);
+        assert!(mine.targets(&board, 1, None) == vec![(Zone::Stable(1), 0), (Zone::Stable(1), 1)]);
+    }
+
+    #[test]
+    fn test_exclusions() {
+        let board = board();
+        let others = selector(r#"{ "cards": "Unicorn", "exclude": ["This", { "Matching": { "Type": "BabyUnicorn" } }] }"#);
+        let this = (Zone::Stable(0), 0);
+        assert!(others.targets(&board, 0, Some(&this)) == vec![(Zone::Stable(1), 0), (Zone::Stable(2), 0)]);
+
+        let err = others.validate(&board, 0, Some(&this), &Zone::Stable(0), 0);
+        assert!(matches!(err, Err(LogicError::InvalidTarget { player: 0, index: 0 })));
+        assert!(others.validate(&board, 0, Some(&this), &Zone::Stable(2), 0).is_ok());
+        let err = others.validate(&board, 0, None, &Zone::Stable(2), 1);
+        assert!(matches!(err, Err(LogicError::CardNotInZone { zone: Zone::Stable(2), index: 1 })));
+    }
+
+    #[test]
+    fn test_other_zones() {
+        let board = board();
+        let hand = selector(r#"{ "zone": "Hand", "owner": "You" }"#);
+        assert!(hand.targets(&board, 0, None) == vec![(Zone::Hand(0), 0), (Zone::Hand(0), 1)]);
+        assert!(hand.targets(&board, 1, None).is_empty());
+        let err = hand.validate(&board, 1, None, &Zone::Hand(0), 0);
+        assert!(matches!(err, Err(LogicError::InvalidChoice { zone: Zone::Hand(0), index: 0 })));
+
+        let neighs = selector(r#"{ "zone": "Deck", "owner": "You", "cards": { "Named": "Neigh" } }"#);
+        assert!(neighs.targets(&board, 2, None).len() == 3, "Nobody owns the deck.");
+    }
+
+    #[test]
+    fn test_dragons() {
+        let mut board = board();
+        board.players[2].stable.push(card_from_name("Red Dragon").unwrap());
+        let at = (Zone::Stable(2), 1);
+        assert!(!Selector::default().targets(&board, 0, None).contains(&at), "Dragons are only reached by type.");
+        assert!(!Selector::from(Filter::Named("Red Dragon".to_string())).targets(&board, 0, None).contains(&at));
+
+        let fire = selector(r#"{ "cards": { "AnyOf": ["Unicorn", { "Type": "Dragon" }] } }"#);
+        assert!(fire.targets(&board, 0, None).contains(&at) && fire.targets(&board, 0, None).len() == 5);
+        let err = Selector::default().validate(&board, 0, None, &Zone::Stable(2), 1);
+        assert!(matches!(err, Err(LogicError::InvalidTarget { player: 2, index: 1 })));
+
+        // Bonded Dragons are Unicorns like any other.
+        board.players[2].stable.push(card_from_name("Dragon Bond").unwrap());
+        let unicorns = Selector::from(Filter::Unicorn);
+        assert!(unicorns.targets(&board, 0, None).contains(&at));
+        assert!(unicorns.validate(&board, 0, None, &Zone::Stable(2), 1).is_ok());
+    }
+}
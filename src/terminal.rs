@@ -0,0 +1,222 @@
+// STD
+use std::io::{self, BufRead, Write};
+use std::path::Path;
+
+// UU
+use crate::cards::Cards;
+use crate::game::Game;
+use crate::modifiers::unicorns;
+use crate::moves::{next_to_act, Move};
+use crate::notation::{format_move, parse_move};
+use crate::save::{save_game, CardSources};
+use crate::state::{Board, GameState};
+
+const CLEAR_SCREEN: &str = "\x1b[2J\x1b[H";
+
+fn card_list(cards: &Cards) -> String {
+    if cards.is_empty() {
+        return "(empty)".to_string();
+    }
+    return cards.iter().map(|c| format!("{:?}", c.name())).collect::<Vec<_>>().join(", ");
+}
+
+/// Everything `viewer` is allowed to see: every stable, the piles and only their own hand.
+pub fn render_view(state: &GameState, viewer: usize) -> String {
+    return render(state, Some(viewer));
+}
+
+/// The whole state with every hand face up, for replays and inspecting saves.
+pub fn render_all(state: &GameState) -> String {
+    return render(state, None);
+}
+
+fn render(state: &GameState, viewer: Option<usize>) -> String {
+    let board = &state.board;
+    let mut view = format!("=== P{}'s turn, {:?} phase ===\n", state.turn + 1, state.phase);
+    view += &format!("Deck: {}  Nursery: {}  Discard: {}", board.deck.len(), board.nursery.len(), board.discard.len());
+    if let Some(top) = board.discard.last() {
+        view += &format!(" (top {:?})", top.name());
+    }
+    view += "\n";
+
+    for (p_idx, p) in board.players.iter().enumerate() {
+        view += &format!("P{} stable [{} unicorns]: {}\n", p_idx + 1, unicorns(&state.board, p_idx), card_list(&p.stable));
+        match viewer {
+            None => view += &format!("P{} hand: {}\n", p_idx + 1, card_list(&p.hand)),
+            Some(viewer) if viewer != p_idx => view += &format!("P{} hand: {} cards\n", p_idx + 1, p.hand.len()),
+            Some(_) => {}
+        }
+    }
+
+    if !state.history.is_empty() {
+        let chain: Vec<_> = state.history.iter().map(|a| format!("{:?}", a.card.name())).collect();
+        view += &format!("Chain: {}\n", chain.join(" <- "));
+    }
+
+    if let Some(viewer) = viewer {
+        let hand: Vec<_> = board.players[viewer].hand.iter().enumerate()
+                                                    .map(|(idx, c)| format!("#{} {:?}", idx, c.name()))
+                                                    .collect();
+        view += &format!("Your hand (P{}): {}\n", viewer + 1, if hand.is_empty() { "(empty)".to_string() } else { hand.join(", ") });
+    }
+    return view;
+}
+
+/// Final words once nobody can move: the winner, or standings when the deck ran out.
+pub fn describe_result(state: &GameState) -> String {
+    if let Some(winner) = state.winner() {
+        return format!("Game over, P{} wins with {} unicorns!", winner + 1, unicorns(&state.board, winner));
+    }
+
+    let counts: Vec<_> = (0..state.board.players.len()).map(|p| unicorns(&state.board, p)).collect();
+    let best = counts.iter().max().cloned().unwrap_or(0);
+    let leaders: Vec<_> = (0..counts.len()).filter(|p| counts[*p] == best).map(|p| format!("P{}", p + 1)).collect();
+    return format!("Game over, nobody can move. Most unicorns ({}): {}", best, leaders.join(", "));
+}
+
+fn read_line<R: BufRead>(input: &mut R) -> io::Result<Option<String>> {
+    let mut line = String::new();
+    if input.read_line(&mut line)? == 0 {
+        return Ok(None);
+    }
+    return Ok(Some(line.trim().to_string()));
+}
+
+// A listed move number, or a move written in notation.
+fn choose(state: &GameState, player: usize, moves: &[Move], line: &str) -> Result<Move, String> {
+    if let Ok(number) = line.parse::<usize>() {
+        return moves.get(number.wrapping_sub(1)).cloned().ok_or(format!("There is no move {}.", number));
+    }
+
+    let mv = parse_move(state, line).map_err(|e| e.to_string())?;
+    if mv.player != player {
+        return Err(format!("It is P{}'s decision.", player + 1));
+    }
+    return Ok(mv);
+}
+
+/// Plays `game` at one keyboard until nobody can move or the input ends.
+/// The screen is cleared whenever another player has to take over, so hands stay hidden.
+/// `save` writes the game to `save_to`, `save <file>` anywhere else.
+pub fn play_hot_seat<R: BufRead, W: Write>(game: &mut Game, sources: &CardSources, save_to: &Path, input: &mut R,
+                                           output: &mut W) -> io::Result<()> {
+    let mut seat = None;
+    loop {
+        let state = game.current_state().clone();
+        let Some((player, moves)) = next_to_act(&state) else {
+            writeln!(output, "{}", describe_result(&state))?;
+            return Ok(());
+        };
+
+        if seat != Some(player) {
+            write!(output, "{}Pass the keyboard to P{} and press enter.", CLEAR_SCREEN, player + 1)?;
+            output.flush()?;
+            if read_line(input)?.is_none() {
+                return Ok(());
+            }
+            write!(output, "{}", CLEAR_SCREEN)?;
+            seat = Some(player);
+        }
+
+        write!(output, "{}", render_view(&state, player))?;
+        if let Some(last) = game.last_action() {
+            writeln!(output, "Last: {}", last)?;
+        }
+        for (idx, mv) in moves.iter().enumerate() {
+            writeln!(output, "  {}) {}", idx + 1, format_move(&state, mv))?;
+        }
+        writeln!(output, "Pick a move by number or notation, or save [file].")?;
+        write!(output, "P{}> ", player + 1)?;
+        output.flush()?;
+
+        let Some(line) = read_line(input)? else {
+            return Ok(());
+        };
+        if let Some(file) = line.strip_prefix("save").filter(|rest| rest.is_empty() || rest.starts_with(' ')) {
+            let path = if file.trim().is_empty() { save_to } else { Path::new(file.trim()) };
+            match save_game(game, sources, path) {
+                Ok(()) => writeln!(output, "Saved to {}.", path.display())?,
+                Err(e) => writeln!(output, "{}", e)?
+            }
+            continue;
+        }
+        let applied = choose(&state, player, &moves, &line)
+                          .and_then(|mv| game.apply(&mv).map_err(|e| e.to_string()));
+        if let Err(message) = applied {
+            writeln!(output, "{}", message)?;
+        }
+    }
+}
+
+/// Asks for the table size then runs a hot-seat game dealt from `sources`.
+pub fn run<R: BufRead, W: Write>(input: &mut R, output: &mut W, seed: [u8; 32], sources: &CardSources, save_to: &Path) -> io::Result<()> {
+    let player_count = loop {
+        write!(output, "How many players (2-8)? ")?;
+        output.flush()?;
+        let Some(line) = read_line(input)? else {
+            return Ok(());
+        };
+        match line.parse::<u8>() {
+            Ok(count) if (2..=8).contains(&count) => break count,
+            _ => writeln!(output, "Please enter a number from 2 to 8.")?
+        }
+    };
+
+    let board = Board::new_with_sets(&sources.sets, player_count);
+    let mut game = Game::new(&board, true, Some(seed)).map_err(io::Error::other)?;
+    return play_hot_seat(&mut game, sources, save_to, input, output);
+}
+
+#[cfg(test)]
+mod TerminalTest {
+    use super::*;
+    use crate::cards::*;
+    use crate::state::*;
+
+    #[test]
+    fn test_view_hides_other_hands() {
+        let mut board = Board::new_base_game(2);
+        board.players[0].hand = vec![card_from_name("Neigh").unwrap()];
+        board.players[1].hand = vec![card_from_name("Unicorn Phoenix").unwrap(), card_from_name("Super Neigh").unwrap()];
+        board.players[1].stable = vec![card_from_name("Baby Unicorn").unwrap()];
+        let state = GameState::new(&board, &PhaseType::Play);
+
+        let view = render_view(&state, 0);
+        assert!(view.contains("Your hand (P1): #0 \"Neigh\""));
+        assert!(view.contains("P2 hand: 2 cards"));
+        assert!(view.contains("P2 stable [1 unicorns]: \"Baby Unicorn\""));
+        assert!(!view.contains("Phoenix") && !view.contains("Super Neigh"));
+
+        let view = render_all(&state);
+        assert!(view.contains("P2 hand: \"Unicorn Phoenix\", \"Super Neigh\""));
+        assert!(!view.contains("Your hand"));
+    }
+
+    #[test]
+    fn test_scripted_game() {
+        // Always take the first listed move, with a few bad answers mixed in.
+        let script = format!("1\n2\n\n0\nP2 draw\n{}", "1\n".repeat(400));
+        let mut output = vec![];
+        run(&mut script.as_bytes(), &mut output, [5; 32], &CardSources::default(), Path::new("unused.json")).unwrap();
+        let output = String::from_utf8(output).unwrap();
+
+        assert!(output.contains("Please enter a number from 2 to 8."));
+        assert!(output.contains("There is no move 0."));
+        assert!(output.contains("It is P1's decision."));
+        assert!(output.contains("Pass the keyboard to P2"));
+        assert!(output.contains("Game over"));
+    }
+
+    #[test]
+    fn test_save_command() {
+        let path = std::env::temp_dir().join(format!("uu_terminal_test_{}.json", std::process::id()));
+        let script = format!("2\n\n1\nsave {}\n", path.display());
+        let mut output = vec![];
+        run(&mut script.as_bytes(), &mut output, [5; 32], &CardSources::default(), Path::new("unused.json")).unwrap();
+        assert!(String::from_utf8(output).unwrap().contains(&format!("Saved to {}.", path.display())));
+
+        let save = crate::save::load_game(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert!(save.moves.len() == 1 && save.sources == CardSources::default());
+    }
+}
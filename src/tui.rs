@@ -0,0 +1,333 @@
+// STD
+use std::io;
+use std::path::PathBuf;
+use std::time::Duration;
+
+// EXT
+use ratatui::crossterm::event::{self, Event, KeyCode, KeyEventKind};
+use ratatui::layout::{Constraint, Flex, Layout, Rect};
+use ratatui::style::{Modifier, Style};
+use ratatui::text::Line;
+use ratatui::widgets::{Block, Clear, List, ListItem, ListState, Paragraph, Wrap};
+use ratatui::Frame;
+
+// UU
+use crate::agent::Agent;
+use crate::game::Game;
+use crate::modifiers;
+use crate::moves::{next_to_act, Move};
+use crate::notation::format_move;
+use crate::save::{save_game, CardSources};
+use crate::state::{GameState, PhaseType, ResponseOp};
+use crate::terminal::describe_result;
+
+// How long autoplay waits between bot moves.
+const TICK: Duration = Duration::from_millis(400);
+
+pub enum Seat {
+    Human,
+    Bot(Box<dyn Agent>)
+}
+
+pub struct TuiApp {
+    game: Game,
+    seats: Vec<Seat>,
+    log: Vec<String>,
+    // Highlighted entry of the move list.
+    selected: usize,
+    // Human whose hand is on screen, None while the keyboard is being handed over.
+    viewer: Option<usize>,
+    autoplay: bool,
+    // Where s saves the game, and where its cards come from.
+    save_to: PathBuf,
+    sources: CardSources,
+    pub quit: bool
+}
+
+impl TuiApp {
+    pub fn new(game: Game, seats: Vec<Seat>, sources: CardSources, save_to: PathBuf) -> Self {
+        return TuiApp { game, seats, log: vec![], selected: 0, viewer: None, autoplay: false, save_to, sources, quit: false };
+    }
+
+    pub fn state(&self) -> &GameState {
+        return self.game.current_state();
+    }
+
+    // Nobody at the table is human, so everything may be shown.
+    fn spectating(&self) -> bool {
+        return self.seats.iter().all(|s| matches!(s, Seat::Bot(_)));
+    }
+
+    fn is_human(&self, player: usize) -> bool {
+        return matches!(self.seats.get(player), Some(Seat::Human));
+    }
+
+    fn apply(&mut self, mv: &Move) {
+        let description = format_move(self.state(), mv);
+        match self.game.apply(mv) {
+            Ok(_) => {
+                // Edges hide drawn cards, which the notation would otherwise reveal.
+                let entry = self.game.last_action().map(|e| e.to_string()).unwrap_or(description);
+                self.log.push(entry);
+                self.selected = 0;
+            },
+            Err(e) => self.log.push(format!("{} rejected: {}", description, e))
+        }
+    }
+
+    /// Lets the bot that is due make one move. Returns false when it is not a bot's turn.
+    pub fn step_bot(&mut self) -> bool {
+        let state = self.state().clone();
+        let Some((player, moves)) = next_to_act(&state) else {
+            return false;
+        };
+        let Some(Seat::Bot(agent)) = self.seats.get_mut(player) else {
+            return false;
+        };
+
+        let choice = agent.choose(&state, player, &moves).min(moves.len() - 1);
+        self.apply(&moves[choice]);
+        return true;
+    }
+
+    pub fn handle_key(&mut self, key: KeyCode) {
+        if key == KeyCode::Char('q') || key == KeyCode::Esc {
+            self.quit = true;
+            return;
+        }
+        if key == KeyCode::Char('s') {
+            match save_game(&self.game, &self.sources, &self.save_to) {
+                Ok(()) => self.log.push(format!("Saved to {}", self.save_to.display())),
+                Err(e) => self.log.push(e.to_string())
+            }
+            return;
+        }
+
+        let Some((player, moves)) = next_to_act(self.state()) else {
+            return;
+        };
+
+        if !self.is_human(player) {
+            match key {
+                KeyCode::Char(' ') | KeyCode::Char('n') | KeyCode::Enter => { self.step_bot(); },
+                KeyCode::Char('a') => self.autoplay = !self.autoplay,
+                _ => {}
+            }
+            return;
+        }
+
+        if self.viewer != Some(player) {
+            // Handover screen, only enter reveals the new hand.
+            if key == KeyCode::Enter {
+                self.viewer = Some(player);
+            }
+            return;
+        }
+
+        match key {
+            KeyCode::Up => self.selected = self.selected.saturating_sub(1),
+            KeyCode::Down => self.selected = (self.selected + 1).min(moves.len() - 1),
+            KeyCode::Enter => {
+                let mv = moves[self.selected.min(moves.len() - 1)].clone();
+                self.apply(&mv);
+            },
+            _ => {}
+        }
+    }
+}
+
+fn card_lines(cards: &crate::cards::Cards) -> Vec<Line<'static>> {
+    return cards.iter().map(|c| Line::from(format!("{} ({:?})", c.name(), c.ctype()))).collect();
+}
+
+fn popup_area(area: Rect, width: u16, height: u16) -> Rect {
+    let [area] = Layout::vertical([Constraint::Length(height)]).flex(Flex::Center).areas(area);
+    let [area] = Layout::horizontal([Constraint::Length(width)]).flex(Flex::Center).areas(area);
+    return area;
+}
+
+pub fn draw(frame: &mut Frame, app: &TuiApp) {
+    let state = app.state();
+    let board = &state.board;
+    let next = next_to_act(state);
+    let shown = match app.spectating() {
+        true => None,
+        false => app.viewer.filter(|v| next.as_ref().is_some_and(|(p, _)| p == v))
+    };
+
+    let [header, middle, footer] = Layout::vertical([
+        Constraint::Length(3), Constraint::Min(8), Constraint::Length(9)
+    ]).areas(frame.area());
+    let [stables, log] = Layout::horizontal([Constraint::Min(30), Constraint::Length(40)]).areas(middle);
+
+    let status = format!(
+        "P{}'s turn, {:?} phase | Deck {} | Nursery {} | Discard {}{}",
+        state.turn + 1, state.phase, board.deck.len(), board.nursery.len(), board.discard.len(),
+        board.discard.last().map(|c| format!(" (top: {})", c.name())).unwrap_or_default()
+    );
+    frame.render_widget(Paragraph::new(status).block(Block::bordered().title("Unstable Unicorns (s: save, q: quit)")), header);
+
+    // Stables side by side, with hands only revealed to spectators.
+    let columns = Layout::horizontal(vec![Constraint::Fill(1); board.players.len()]).split(stables);
+    for (p_idx, p) in board.players.iter().enumerate() {
+        let unicorns = modifiers::unicorns(board, p_idx);
+        let mut lines = card_lines(&p.stable);
+        lines.push(Line::from(""));
+        if app.spectating() {
+            lines.push(Line::from("Hand:").style(Style::new().add_modifier(Modifier::BOLD)));
+            lines.extend(card_lines(&p.hand));
+        } else {
+            lines.push(Line::from(format!("Hand: {} cards", p.hand.len())));
+        }
+
+        let mut block = Block::bordered().title(format!("P{} stable ({} unicorns)", p_idx + 1, unicorns));
+        if p_idx == state.turn {
+            block = block.border_style(Style::new().add_modifier(Modifier::BOLD));
+        }
+        frame.render_widget(Paragraph::new(lines).block(block), columns[p_idx]);
+    }
+
+    let entries: Vec<Line> = app.log.iter().rev().take(log.height as usize).rev().map(|e| Line::from(e.as_str())).collect();
+    frame.render_widget(Paragraph::new(entries).block(Block::bordered().title("Events")), log);
+
+    // Bottom pane: the acting human's hand and moves, or the controls when watching bots.
+    let mut moves_state = ListState::default();
+    let (title, items): (String, Vec<ListItem>) = match (&next, shown) {
+        (Some((player, moves)), Some(viewer)) if viewer == *player => {
+            moves_state.select(Some(app.selected.min(moves.len() - 1)));
+            let hand: Vec<_> = board.players[viewer].hand.iter().map(|c| c.name()).collect();
+            let items = moves.iter().map(|m| ListItem::new(format_move(state, m))).collect();
+            (format!("P{} hand: {}", viewer + 1, hand.join(", ")), items)
+        },
+        (Some((player, _)), _) if app.is_human(*player) => {
+            (format!("Pass the keyboard to P{} and press enter", player + 1), vec![])
+        },
+        (Some((player, _)), _) => {
+            let name = match &app.seats[*player] { Seat::Bot(agent) => agent.name(), Seat::Human => String::new() };
+            let mode = if app.autoplay { "autoplay on" } else { "space: step, a: autoplay" };
+            (format!("P{} ({}) is thinking ({}, q: quit)", player + 1, name, mode), vec![])
+        },
+        (None, _) => ("Game over (q: quit)".to_string(), vec![])
+    };
+
+    let neigh_window = state.phase == PhaseType::React
+                       && state.react_metadata.as_ref().is_some_and(|m| m.follow_up == ResponseOp::Neigh);
+    let list = List::new(items.clone()).highlight_symbol("> ").highlight_style(Style::new().add_modifier(Modifier::REVERSED));
+    if neigh_window && !items.is_empty() {
+        // Reactions happen out of turn, so they get a prompt of their own.
+        frame.render_widget(Block::bordered().title(title), footer);
+        let chain: Vec<_> = state.history.iter().map(|a| a.card.name()).collect();
+        let area = popup_area(frame.area(), 60, items.len() as u16 + 4);
+        frame.render_widget(Clear, area);
+        let prompt = format!("P{}, react to {}?", shown.unwrap() + 1, chain.join(" <- "));
+        frame.render_stateful_widget(list.block(Block::bordered().title(prompt)), area, &mut moves_state);
+    } else {
+        frame.render_stateful_widget(list.block(Block::bordered().title(title)), footer, &mut moves_state);
+    }
+
+    if next.is_none() {
+        let area = popup_area(frame.area(), 70, 3);
+        frame.render_widget(Clear, area);
+        let result = Paragraph::new(describe_result(state)).wrap(Wrap { trim: true });
+        frame.render_widget(result.block(Block::bordered()), area);
+    }
+}
+
+/// Runs the full-screen interface until the user quits, saving to `save_to` when asked.
+pub fn run(game: Game, seats: Vec<Seat>, sources: CardSources, save_to: PathBuf) -> io::Result<()> {
+    let mut app = TuiApp::new(game, seats, sources, save_to);
+    let mut terminal = ratatui::init();
+    let result = (|| -> io::Result<()> {
+        while !app.quit {
+            terminal.draw(|frame| draw(frame, &app))?;
+            if event::poll(TICK)? {
+                if let Event::Key(key) = event::read()? {
+                    if key.kind == KeyEventKind::Press {
+                        app.handle_key(key.code);
+                    }
+                }
+            } else if app.autoplay {
+                app.step_bot();
+            }
+        }
+        return Ok(());
+    })();
+    ratatui::restore();
+    return result;
+}
+
+#[cfg(test)]
+mod TuiTest {
+    use super::*;
+    use crate::agent::FirstAgent;
+    use crate::cards::*;
+    use crate::state::*;
+    use ratatui::backend::TestBackend;
+    use ratatui::Terminal;
+
+    fn screen(app: &TuiApp) -> String {
+        let mut terminal = Terminal::new(TestBackend::new(140, 40)).unwrap();
+        terminal.draw(|frame| draw(frame, app)).unwrap();
+        let buffer = terminal.backend().buffer();
+        let mut text = String::new();
+        for y in 0..buffer.area.height {
+            for x in 0..buffer.area.width {
+                text += buffer[(x, y)].symbol();
+            }
+            text += "\n";
+        }
+        return text;
+    }
+
+    fn scenario() -> Game {
+        let mut board = Board::new_base_game(2);
+        board.players[0].hand = vec![card_from_name("Basic Unicorn").unwrap()];
+        board.players[1].hand = vec![card_from_name("Neigh").unwrap()];
+        return Game::new(&board, true, None).unwrap();
+    }
+
+    #[test]
+    fn test_hot_seat_screens() {
+        let mut app = TuiApp::new(scenario(), vec![Seat::Human, Seat::Human], CardSources::default(), PathBuf::from("unused.json"));
+        let text = screen(&app);
+        assert!(text.contains("Pass the keyboard to P1"));
+        assert!(!text.contains("Basic Unicorn"), "Hands stay hidden until enter.");
+
+        app.handle_key(KeyCode::Enter);
+        app.handle_key(KeyCode::Enter);
+        assert!(screen(&app).contains("P1 play \"Basic Unicorn\""));
+
+        // Play the unicorn, then P2 gets the reaction prompt after the handover.
+        app.handle_key(KeyCode::Enter);
+        let text = screen(&app);
+        assert!(text.contains("Pass the keyboard to P2"));
+        app.handle_key(KeyCode::Enter);
+        let text = screen(&app);
+        assert!(text.contains("P2, react to Basic Unicorn?"));
+        assert!(text.contains("P2 react \"Neigh\""));
+        assert!(text.contains("P1 play \"Basic Unicorn\""), "Event log should list the play.");
+    }
+
+    #[test]
+    fn test_watch_bots() {
+        let path = std::env::temp_dir().join(format!("uu_tui_test_{}.json", std::process::id()));
+        let bots = vec![Seat::Bot(Box::new(FirstAgent {})), Seat::Bot(Box::new(FirstAgent {}))];
+        let mut app = TuiApp::new(scenario(), bots, CardSources::default(), path.clone());
+        assert!(screen(&app).contains("Neigh"), "Spectators see every hand.");
+
+        app.handle_key(KeyCode::Char(' '));
+        assert!(app.log == vec!["P1 draw".to_string()]);
+        app.handle_key(KeyCode::Char('s'));
+        assert!(app.log[1] == format!("Saved to {}", path.display()));
+        let saved = crate::save::load_game(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert!(saved.moves.len() == 1);
+
+        while app.step_bot() {}
+        assert!(next_to_act(app.state()).is_none());
+        assert!(screen(&app).contains("Game over"));
+
+        app.handle_key(KeyCode::Char('q'));
+        assert!(app.quit);
+    }
+}